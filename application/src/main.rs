@@ -5,9 +5,9 @@ use ws_socket::start;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    let r = start().await;
-    if r.is_err() {
-        println!("Error: {:?}", r.unwrap_err());
+    if let Err(error) = start().await {
+        println!("Error: {:?}", error);
+        std::process::exit(error.exit_code());
     }
     Ok(())
 }
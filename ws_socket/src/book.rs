@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+
+use crate::types::{BookLevel, BookPrice, Decimal, PriceUpdate, Ticker, WSResult};
+
+/// levels of each side in the okx checksum
+const CHECKSUM_DEPTH: usize = 25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// side of an order book
+pub enum BookSide {
+    Bid,
+    Ask,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// best bid and best ask of a book with their size
+pub struct BookTop {
+    pub bid: Decimal,
+    pub bid_size: Decimal,
+    pub ask: Decimal,
+    pub ask_size: Decimal,
+}
+
+impl BookTop {
+    /// middle of best bid and best ask, none when it overflows
+    pub fn mid(&self) -> Option<Decimal> {
+        let sum = self.bid.checked_add(self.ask)?;
+        Some(sum.checked_div(Decimal::TWO)?.normalize())
+    }
+
+    /// mid weighted by the size of the other side, it moves towards the thinner side
+    ///
+    /// Mid when both sizes are zero, none when it overflows.
+    pub fn microprice(&self) -> Option<Decimal> {
+        let size = self.bid_size.checked_add(self.ask_size)?;
+        let amount = self
+            .bid
+            .checked_mul(self.ask_size)?
+            .checked_add(self.ask.checked_mul(self.bid_size)?)?;
+        if size.is_zero() {
+            return self.mid();
+        }
+        Some(amount.checked_div(size)?.normalize())
+    }
+
+    /// book price of the kind, none when it overflows
+    pub fn price(&self, book_price: BookPrice) -> Option<Decimal> {
+        match book_price {
+            BookPrice::Mid => self.mid(),
+            BookPrice::Microprice => self.microprice(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+/// level 2 order book of one symbol, levels keep price and size text of the exchange
+pub struct OrderBook {
+    bids: BTreeMap<Decimal, BookLevel>,
+    asks: BTreeMap<Decimal, BookLevel>,
+}
+
+impl OrderBook {
+    /// book of snapshot levels
+    pub fn from_levels(bids: &[BookLevel], asks: &[BookLevel]) -> WSResult<OrderBook> {
+        let mut book = OrderBook::default();
+        for (price, size) in bids {
+            book.apply(BookSide::Bid, price, size)?;
+        }
+        for (price, size) in asks {
+            book.apply(BookSide::Ask, price, size)?;
+        }
+        Ok(book)
+    }
+
+    /// set size of the price level, zero size removes it
+    pub fn apply(&mut self, side: BookSide, price: &str, size: &str) -> WSResult<()> {
+        let key = Decimal::from_str_exact(price)?;
+        let levels = match side {
+            BookSide::Bid => &mut self.bids,
+            BookSide::Ask => &mut self.asks,
+        };
+        if Decimal::from_str_exact(size)?.is_zero() {
+            levels.remove(&key);
+        } else {
+            levels.insert(key, (price.to_string(), size.to_string()));
+        }
+        Ok(())
+    }
+
+    /// top levels of the side, best first
+    pub fn levels(&self, side: BookSide, depth: usize) -> Vec<&BookLevel> {
+        match side {
+            BookSide::Bid => self.bids.values().rev().take(depth).collect(),
+            BookSide::Ask => self.asks.values().take(depth).collect(),
+        }
+    }
+
+    /// best bid and best ask, none when a side is empty
+    pub fn top(&self) -> Option<BookTop> {
+        let (bid, (_, bid_size)) = self.bids.iter().next_back()?;
+        let (ask, (_, ask_size)) = self.asks.iter().next()?;
+        Some(BookTop {
+            bid: *bid,
+            bid_size: Decimal::from_str_exact(bid_size).ok()?,
+            ask: *ask,
+            ask_size: Decimal::from_str_exact(ask_size).ok()?,
+        })
+    }
+
+    /// best bid is at or above best ask, the book missed an update
+    pub fn is_crossed(&self) -> bool {
+        match (self.bids.keys().next_back(), self.asks.keys().next()) {
+            (Some(bid), Some(ask)) => bid >= ask,
+            _ => false,
+        }
+    }
+
+    /// signed crc32 of the top levels as okx computes it, `bid:size:ask:size` alternating by level
+    pub fn okx_checksum(&self) -> i32 {
+        let bids = self.levels(BookSide::Bid, CHECKSUM_DEPTH);
+        let asks = self.levels(BookSide::Ask, CHECKSUM_DEPTH);
+        let mut fields: Vec<&str> = vec![];
+        for depth in 0..CHECKSUM_DEPTH {
+            for levels in [&bids, &asks] {
+                if let Some((price, size)) = levels.get(depth) {
+                    fields.push(price);
+                    fields.push(size);
+                }
+            }
+        }
+        crc32fast::hash(fields.join(":").as_bytes()) as i32
+    }
+
+    /// price of the book top for the pairs cache, none when a side is empty or the price overflows
+    pub fn price_update(
+        &self,
+        key: String,
+        book_price: BookPrice,
+        event_time: Option<u64>,
+    ) -> Option<PriceUpdate> {
+        let top = self.top()?;
+        let (bid, bid_size) = self.bids.values().next_back()?.clone();
+        let (ask, ask_size) = self.asks.values().next()?.clone();
+        Some(PriceUpdate {
+            key,
+            ticker: Ticker {
+                last: top.price(book_price)?.to_string(),
+                bid: Some(bid),
+                ask: Some(ask),
+                bid_size: Some(bid_size),
+                ask_size: Some(ask_size),
+                volume_24h: None,
+                event_time,
+            },
+            received: None,
+            conversion: None,
+            trade: None,
+            market: None,
+        })
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::types::{Conversion, Decimal, Pair, PriceUpdate};
+
+/// quote the venue lists for the requested quote, none when it is not bridged
+fn venue_quote<'a>(bridges: &'a HashMap<String, String>, quote: &str) -> Option<&'a str> {
+    bridges
+        .iter()
+        .find(|(requested, _)| requested.eq_ignore_ascii_case(quote))
+        .map(|(_, venue)| venue.as_str())
+}
+
+/// pairs subscribed on the venue, a bridged pair is subscribed in the venue quote with its conversion pair
+///
+/// `btc_usdt` with bridge `USDT` to `USD` gives `BTC_USD` and `USDT_USD`.
+pub fn venue_pairs(bridges: &HashMap<String, String>, pairs: &[String]) -> Vec<String> {
+    let mut venue_pairs: Vec<String> = vec![];
+    for pair in pairs {
+        let bridged = Pair::parse(pair).and_then(|pair| {
+            let venue = venue_quote(bridges, &pair.quote)?;
+            Some([
+                Pair::new(&pair.base, venue).to_string(),
+                Pair::new(&pair.quote, venue).to_string(),
+            ])
+        });
+        let pairs = match bridged {
+            Some(pairs) => pairs.to_vec(),
+            None => vec![pair.clone()],
+        };
+        for pair in pairs {
+            if !venue_pairs.contains(&pair) {
+                venue_pairs.push(pair);
+            }
+        }
+    }
+    venue_pairs
+}
+
+#[derive(Debug, Default)]
+/// converts prices of bridged pairs into the requested quote with the last rate of the conversion pair
+pub struct QuoteBridge {
+    /// pairs cache key of a venue pair to the requested key and the conversion pair
+    targets: HashMap<String, (String, Pair)>,
+    /// pairs cache key of every conversion pair
+    conversions: HashMap<String, Pair>,
+    /// keys of pairs which are requested without bridge
+    requested: HashSet<String>,
+    /// last rate of each conversion pair, kept when pairs change
+    rates: HashMap<String, Decimal>,
+}
+
+impl QuoteBridge {
+    pub fn new(bridges: &HashMap<String, String>, pairs: &[String]) -> QuoteBridge {
+        let mut bridge = QuoteBridge::default();
+        bridge.set_pairs(bridges, pairs);
+        bridge
+    }
+
+    /// build the lookups again for the requested pairs
+    pub fn set_pairs(&mut self, bridges: &HashMap<String, String>, pairs: &[String]) {
+        self.targets.clear();
+        self.conversions.clear();
+        self.requested.clear();
+        for pair in pairs.iter().filter_map(|pair| Pair::parse(pair)) {
+            match venue_quote(bridges, &pair.quote) {
+                Some(venue) => {
+                    let conversion = Pair::new(&pair.quote, venue);
+                    self.conversions
+                        .insert(conversion.key(), conversion.clone());
+                    self.targets
+                        .insert(Pair::new(&pair.base, venue).key(), (pair.key(), conversion));
+                }
+                None => {
+                    self.requested.insert(pair.key());
+                }
+            }
+        }
+    }
+
+    /// keep rates of conversion pairs and move bridged prices to the requested key
+    ///
+    /// Prices are dropped until the rate of their conversion pair is known.
+    pub fn convert(&mut self, prices: Vec<PriceUpdate>) -> Vec<PriceUpdate> {
+        if self.targets.is_empty() {
+            return prices;
+        }
+
+        let mut converted = vec![];
+        for update in prices {
+            let bridged = self.conversions.contains_key(&update.key)
+                || self.targets.contains_key(&update.key);
+            if self.conversions.contains_key(&update.key) {
+                match Decimal::from_str_exact(&update.ticker.last) {
+                    Ok(rate) if !rate.is_zero() => {
+                        self.rates.insert(update.key.clone(), rate);
+                    }
+                    _ => {}
+                }
+            }
+            if let Some((key, conversion)) = self.targets.get(&update.key) {
+                if let Some(rate) = self.rates.get(&conversion.key()) {
+                    let mut bridged = update.clone();
+                    bridged.key.clone_from(key);
+                    bridged.conversion = Some(Conversion {
+                        pair: conversion.to_string(),
+                        rate: *rate,
+                    });
+                    converted.push(bridged);
+                }
+            }
+            // conversion pair and venue pair are only kept when they are requested too
+            if !bridged || self.requested.contains(&update.key) {
+                converted.push(update);
+            }
+        }
+        converted
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    time::Duration,
+};
+
+use url::Url;
+
+use crate::{
+    errors::WSError,
+    exchanges::{split_market, Exchange, ExchangeRegistry},
+    proxy,
+    types::{MarketType, WSDetails, WSResult, WebSocketConfig},
+};
+
+/// read socket config file and validate it against the registry
+pub fn load_ws_details(path: &str, registry: &ExchangeRegistry) -> WSResult<WSDetails> {
+    let ws_details_file: File = fs::File::open(path)?;
+    let ws_details: Vec<WebSocketConfig> = serde_json::from_reader(&ws_details_file)?;
+
+    validate_ws_details(ws_details, registry)
+}
+
+/// key socket configs by name, reject unknown and duplicate names, bad urls and missing req_param fields,
+/// a name which is not registered needs a generic mapping
+pub fn validate_ws_details(
+    ws_details: Vec<WebSocketConfig>,
+    registry: &ExchangeRegistry,
+) -> WSResult<WSDetails> {
+    let mut configs: WSDetails = HashMap::new();
+
+    for config in ws_details {
+        let builder = registry
+            .builder(&config)
+            .ok_or_else(|| WSError::UnknownExchange(config.name.clone()))?;
+
+        if configs.contains_key(&config.name) {
+            return Err(WSError::DuplicateExchange(config.name));
+        }
+
+        validate_config(builder(&config).as_ref())?;
+        configs.insert(config.name.clone(), config);
+    }
+    Ok(configs)
+}
+
+/// check socket url and required req_param fields of an exchange
+pub fn validate_config(exchange: &dyn Exchange) -> WSResult<()> {
+    let config = exchange.config();
+
+    for ws_url in std::iter::once(&config.ws_base_url).chain(&config.futures_ws_base_url) {
+        let invalid_url = || WSError::InvalidUrl(config.name.clone(), ws_url.clone());
+        let url = Url::parse(ws_url).map_err(|_| invalid_url())?;
+        if url.scheme() != "ws" && url.scheme() != "wss" {
+            return Err(invalid_url());
+        }
+    }
+
+    for field in exchange.req_param_fields() {
+        if config.req_param.get(field).is_none() {
+            return Err(WSError::MissingReqParam(
+                config.name.clone(),
+                field.to_string(),
+            ));
+        }
+    }
+    if !exchange.streams().contains(&config.stream) {
+        return Err(WSError::UnsupportedStream(
+            config.name.clone(),
+            config.stream.to_string(),
+        ));
+    }
+    // market suffix of the name is the market of the config
+    let named_market = split_market(&config.name).1;
+    if !exchange.markets().contains(&config.market)
+        || named_market.is_some_and(|market| market != config.market)
+    {
+        return Err(WSError::UnsupportedMarket(
+            config.name.clone(),
+            config.market.to_string(),
+        ));
+    }
+    if config.market == MarketType::Futures {
+        let expiry = config.expiry.clone().unwrap_or_default();
+        if expiry.len() != 6 || !expiry.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(WSError::InvalidExpiry(config.name.clone(), expiry));
+        }
+    }
+    // tokio intervals panic on a zero period
+    if exchange.keepalive().interval() == Some(Duration::ZERO) {
+        return Err(WSError::ZeroInterval(
+            config.name.clone(),
+            "keepalive.interval_ms".to_string(),
+        ));
+    }
+    if config
+        .stale
+        .as_ref()
+        .is_some_and(|stale| stale.timeout_ms == 0)
+    {
+        return Err(WSError::ZeroInterval(
+            config.name.clone(),
+            "stale.timeout_ms".to_string(),
+        ));
+    }
+    if let Some(setting) = &config.proxy {
+        if setting != proxy::DIRECT {
+            proxy::parse_proxy(&config.name, setting)?;
+        }
+    }
+    exchange.validate()
+}
@@ -12,11 +12,72 @@ pub enum WSError {
     #[error("Parse Error")]
     ParseError(#[from] ParseError),
     #[error("Tungsnite Error")]
-    TungsniteError(#[from] TError),
+    TungsniteError(Box<TError>),
     #[error("ParseFloatError")]
     ParseFloatError(#[from] ParseFloatError),
+    #[error("Decimal Error: {0}")]
+    DecimalError(#[from] rust_decimal::Error),
     #[error("Got Unknown Response")]
     UnknownResponse,
     #[error("Socket Response Error:{0}")]
     SocketResponseError(String),
+    #[error("Socket is not connected")]
+    SocketNotConnected,
+    #[error("Unknown exchange in config: {0}")]
+    UnknownExchange(String),
+    #[error("Duplicate exchange in config: {0}")]
+    DuplicateExchange(String),
+    #[error("Invalid socket url for {0}: {1}")]
+    InvalidUrl(String, String),
+    #[error("Missing req_param field for {0}: {1}")]
+    MissingReqParam(String, String),
+    #[error("Invalid generic mapping for {0}: {1}")]
+    InvalidGenericConfig(String, String),
+    #[error("Reconnect failed for {0} after {1} attempts")]
+    ReconnectFailed(String, u32),
+    #[error("No exchange configured")]
+    NoExchangeConfigured,
+    #[error("Interval {1} must not be zero for {0}")]
+    ZeroInterval(String, String),
+    #[error("Invalid proxy for {0}: {1}")]
+    InvalidProxy(String, String),
+    #[error("Proxy error: {0}")]
+    ProxyError(String),
+    #[error("Tls error: {0}")]
+    TlsError(String),
+    #[error("Certificate pin mismatch for {0}")]
+    PinMismatch(String),
+    #[error("Stopped by signal {0}")]
+    Interrupted(i32),
+    #[error("Connect timed out for {0}")]
+    ConnectTimeout(String),
+    #[error("No exchange connected")]
+    NoExchangeConnected,
+    #[error("Stream {1} is not supported for {0}")]
+    UnsupportedStream(String, String),
+    #[error("Unsubscribe is not supported for {0}")]
+    UnsubscribeNotSupported(String),
+    #[error("Book snapshot failed for {0}: {1}")]
+    SnapshotFailed(String, String),
+    #[error("Market {1} is not supported for {0}")]
+    UnsupportedMarket(String, String),
+    #[error("Invalid futures expiry for {0}: {1}")]
+    InvalidExpiry(String, String),
+}
+
+// tungstenite error is large, keep it boxed so every WSResult stays small
+impl From<TError> for WSError {
+    fn from(error: TError) -> Self {
+        WSError::TungsniteError(Box::new(error))
+    }
+}
+
+impl WSError {
+    /// process exit status for the error, 128 + signal number when stopped by a signal
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            WSError::Interrupted(signal) => 128 + signal,
+            _ => 1,
+        }
+    }
 }
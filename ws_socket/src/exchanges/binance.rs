@@ -0,0 +1,444 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+use url::Url;
+
+use super::Exchange;
+use crate::{
+    book::{BookSide, OrderBook},
+    helpers,
+    types::{
+        BinanceDepth, BinanceDepthSnapshot, BinanceMarkPrice, BinanceReqParam, BinanceResponse,
+        BinanceTrade, DerivativeUpdate, MarketType, PriceUpdate, ResponseEnum, StreamKind,
+        SubscribeAck, Ticker, Trade, TradeSide, WSResult, WebSocketConfig,
+    },
+};
+
+/// rest api of the depth snapshots when config does not set one
+const REST_BASE_URL: &str = "https://api.binance.com";
+
+/// socket of usd-m swap and futures streams when config does not set one
+const FUTURES_WS_BASE_URL: &str = "wss://fstream.binance.com";
+
+/// rest api of usd-m depth snapshots when config does not set one
+const FUTURES_REST_BASE_URL: &str = "https://fapi.binance.com";
+
+/// levels of the depth snapshot
+const SNAPSHOT_LIMIT: usize = 1000;
+
+/// diffs kept for a book while its snapshot loads, oldest are dropped first
+const MAX_PENDING: usize = 1000;
+
+#[derive(Debug, Clone, Default)]
+/// local book of a diff depth stream, synced from the rest snapshot
+struct DepthBook {
+    book: OrderBook,
+    /// last update id in the book, none until the snapshot is loaded
+    last_update_id: Option<u64>,
+    /// diffs read before the snapshot
+    pending: Vec<BinanceDepth>,
+    /// last update id is of a diff, not of the snapshot
+    diffed: bool,
+}
+
+impl DepthBook {
+    /// apply the diff on the synced book, false when an update is missing or the book is crossed
+    ///
+    /// Spot diffs follow when `U` is the last `u` plus one, usd-m diffs when `pu` is the last `u`.
+    fn apply(&mut self, depth: &BinanceDepth, market: MarketType) -> WSResult<bool> {
+        let last_update_id = match self.last_update_id {
+            Some(last_update_id) => last_update_id,
+            None => return Ok(false),
+        };
+        // diffs up to the snapshot are already in it, usd-m starts with the one ending on it
+        let applied = match market {
+            MarketType::Spot => depth.u <= last_update_id,
+            _ => depth.u < last_update_id,
+        };
+        if applied {
+            return Ok(true);
+        }
+        let follows = match (market, depth.pu) {
+            (MarketType::Spot, _) | (_, None) => depth.first_update_id <= last_update_id + 1,
+            (_, Some(pu)) if self.diffed => pu == last_update_id,
+            _ => depth.first_update_id <= last_update_id,
+        };
+        if !follows {
+            return Ok(false);
+        }
+        for (price, size) in &depth.b {
+            self.book.apply(BookSide::Bid, price, size)?;
+        }
+        for (price, size) in &depth.a {
+            self.book.apply(BookSide::Ask, price, size)?;
+        }
+        self.last_update_id = Some(depth.u);
+        self.diffed = true;
+        Ok(!self.book.is_crossed())
+    }
+}
+
+#[derive(Debug, Clone)]
+/// binance spot ticker adapter
+pub struct Binance {
+    config: WebSocketConfig,
+    /// id of the next request on a connected socket, first subscribe use id of config
+    next_id: i32,
+    /// symbols of each later subscribe request by its id, results of other ids are not acks
+    subscribes: HashMap<i64, Vec<String>>,
+    /// order book of each symbol of the depth stream
+    books: HashMap<String, DepthBook>,
+}
+
+impl Binance {
+    pub fn new(config: &WebSocketConfig) -> Binance {
+        let id = config.req_param["id"].as_i64().unwrap_or_default() as i32;
+        Binance {
+            config: config.clone(),
+            next_id: id + 1,
+            subscribes: HashMap::new(),
+            books: HashMap::new(),
+        }
+    }
+
+    /// subscribe request of the pairs with a new id and method
+    fn request(&mut self, method: &str, pairs: &[String]) -> WSResult<Vec<String>> {
+        let mut req_param: BinanceReqParam = serde_json::from_value(self.config.req_param.clone())?;
+        req_param.method = method.to_string();
+        req_param.id = self.next_id;
+        self.next_id += 1;
+
+        let symbols = helpers::exchange_symbols(self, pairs);
+        for symbol in &symbols {
+            req_param.params.extend(self.stream_names(symbol));
+        }
+        if method == "SUBSCRIBE" {
+            self.subscribes.insert(req_param.id.into(), symbols);
+        }
+        Ok(vec![serde_json::to_string(&req_param)?])
+    }
+
+    /// streams of the symbol, mark price stream follows when it is subscribed
+    fn stream_names(&self, symbol: &str) -> Vec<String> {
+        let mut streams = vec![format!("{}@{}", symbol, self.stream())];
+        if self.config.mark_funding && self.config.market != MarketType::Spot {
+            streams.push(format!("{}@markPrice", symbol));
+        }
+        streams
+    }
+
+    /// socket url of the market, usd-m swap and futures have their own
+    fn ws_base_url(&self) -> &str {
+        match self.config.market {
+            MarketType::Spot => &self.config.ws_base_url,
+            _ => self
+                .config
+                .futures_ws_base_url
+                .as_deref()
+                .unwrap_or(FUTURES_WS_BASE_URL),
+        }
+    }
+
+    /// stream name of the configured stream kind
+    fn stream(&self) -> &str {
+        match self.config.stream {
+            StreamKind::Ticker => "ticker",
+            StreamKind::Trades => "trade",
+            StreamKind::AggTrades => "aggTrade",
+            StreamKind::Book => "depth",
+        }
+    }
+
+    /// apply the diff on the book of its symbol, the first diff of a book asks for the snapshot
+    fn depth_update(&mut self, depth: BinanceDepth) -> WSResult<ResponseEnum> {
+        let symbol = depth.s.clone();
+        let event_time = depth.event_time;
+        let market = self.config.market;
+        let book = self.books.entry(symbol.clone()).or_default();
+        if book.last_update_id.is_none() {
+            if book.pending.len() == MAX_PENDING {
+                book.pending.remove(0);
+            }
+            book.pending.push(depth);
+            // snapshot is asked once, later diffs wait for it
+            if book.pending.len() == 1 {
+                return Ok(ResponseEnum::Resync(symbol));
+            }
+            return Ok(ResponseEnum::Ignore);
+        }
+        if !book.apply(&depth, market)? {
+            *book = DepthBook {
+                pending: vec![depth],
+                ..Default::default()
+            };
+            return Ok(ResponseEnum::Resync(symbol));
+        }
+        Ok(self.book_prices(&symbol, event_time))
+    }
+
+    /// price of the book top of the symbol
+    fn book_prices(&self, symbol: &str, event_time: Option<u64>) -> ResponseEnum {
+        let update = self.books.get(symbol).and_then(|book| {
+            book.book.price_update(
+                self.normalise_symbol(symbol),
+                self.config.book_price,
+                event_time,
+            )
+        });
+        ResponseEnum::Prices(update.into_iter().collect())
+    }
+
+    /// price of a trade or aggregate trade
+    fn trade_update(&self, trade: BinanceTrade) -> PriceUpdate {
+        let id = if trade.e == "aggTrade" {
+            trade.a
+        } else {
+            trade.t
+        };
+        PriceUpdate {
+            key: self.normalise_symbol(&trade.s),
+            ticker: Ticker {
+                event_time: Some(trade.trade_time),
+                ..Ticker::new(trade.p.clone())
+            },
+            received: None,
+            conversion: None,
+            trade: Some(Trade {
+                id: id.unwrap_or_default().to_string(),
+                price: trade.p,
+                size: trade.q,
+                side: if trade.m {
+                    TradeSide::Sell
+                } else {
+                    TradeSide::Buy
+                },
+            }),
+            market: None,
+        }
+    }
+
+    /// mark price, index price and funding rate of the symbol
+    fn mark_price_update(&self, mark_price: BinanceMarkPrice) -> DerivativeUpdate {
+        DerivativeUpdate {
+            key: self.normalise_symbol(&mark_price.s),
+            mark_price: Some(mark_price.p),
+            index_price: mark_price.i,
+            funding_rate: mark_price.r.filter(|rate| !rate.is_empty()),
+            funding_time: mark_price.funding_time.filter(|time| *time > 0),
+            event_time: mark_price.event_time,
+            received: None,
+        }
+    }
+
+    /// builder for exchange registry
+    pub fn build(config: &WebSocketConfig) -> Box<dyn Exchange> {
+        Box::new(Binance::new(config))
+    }
+
+    /// binance web socket request url handle for pairs and return
+    pub fn req_url(&self, pairs: &[String]) -> String {
+        let mut binance_ws_api: String = format!("{}/ws", self.ws_base_url());
+
+        for symbol in helpers::exchange_symbols(self, pairs) {
+            for stream in self.stream_names(&symbol.to_lowercase()) {
+                binance_ws_api.push_str(&format!("/{}", stream));
+            }
+        }
+
+        binance_ws_api
+    }
+}
+
+impl Exchange for Binance {
+    fn config(&self) -> &WebSocketConfig {
+        &self.config
+    }
+
+    fn req_param_fields(&self) -> &[&str] {
+        &["method", "params", "id"]
+    }
+
+    fn ws_url(&self, pairs: &[String]) -> WSResult<Url> {
+        Ok(Url::parse(&self.req_url(pairs))?)
+    }
+
+    fn add_params(&mut self, pairs: &[String]) -> WSResult<Vec<String>> {
+        self.request("SUBSCRIBE", pairs)
+    }
+
+    fn remove_params(&mut self, pairs: &[String]) -> WSResult<Vec<String>> {
+        self.request("UNSUBSCRIBE", pairs)
+    }
+
+    // streams are in the url path, binance allow 1024 streams per connection
+    fn default_max_pairs(&self) -> Option<usize> {
+        Some(200)
+    }
+
+    fn has_subscribe_ack(&self) -> bool {
+        true
+    }
+
+    // pairs of earlier requests are in the first subscribe after reconnect
+    fn reset(&mut self) {
+        self.subscribes.clear();
+        self.books.clear();
+    }
+
+    fn streams(&self) -> &[StreamKind] {
+        &[
+            StreamKind::Ticker,
+            StreamKind::Trades,
+            StreamKind::AggTrades,
+            StreamKind::Book,
+        ]
+    }
+
+    fn markets(&self) -> &[MarketType] {
+        &[MarketType::Spot, MarketType::Swap, MarketType::Futures]
+    }
+
+    fn snapshot_url(&self, symbol: &str) -> WSResult<Option<Url>> {
+        let (base_url, path) = match self.config.market {
+            MarketType::Spot => (REST_BASE_URL, "/api/v3/depth"),
+            _ => (FUTURES_REST_BASE_URL, "/fapi/v1/depth"),
+        };
+        let base_url = self.config.rest_base_url.as_deref().unwrap_or(base_url);
+        let url = format!(
+            "{}{}?symbol={}&limit={}",
+            base_url,
+            path,
+            symbol.to_uppercase(),
+            SNAPSHOT_LIMIT
+        );
+        Ok(Some(Url::parse(&url)?))
+    }
+
+    /// book of the snapshot with the diffs read before it
+    fn apply_snapshot(&mut self, symbol: &str, snapshot: Value) -> WSResult<ResponseEnum> {
+        let snapshot: BinanceDepthSnapshot = serde_json::from_value(snapshot)?;
+        let pending = self
+            .books
+            .remove(symbol)
+            .map(|book| book.pending)
+            .unwrap_or_default();
+        let mut book = DepthBook {
+            book: OrderBook::from_levels(&snapshot.bids, &snapshot.asks)?,
+            last_update_id: Some(snapshot.last_update_id),
+            ..Default::default()
+        };
+        let mut event_time = None;
+        for depth in &pending {
+            // snapshot is older than the first diff, diffs wait for the next one
+            if !book.apply(depth, self.config.market)? {
+                self.books.insert(
+                    symbol.to_string(),
+                    DepthBook {
+                        pending,
+                        ..Default::default()
+                    },
+                );
+                return Ok(ResponseEnum::Resync(symbol.to_string()));
+            }
+            event_time = depth.event_time.or(event_time);
+        }
+        self.books.insert(symbol.to_string(), book);
+        Ok(self.book_prices(symbol, event_time))
+    }
+
+    fn subscribe_params(&self, pairs: &[String]) -> WSResult<Vec<String>> {
+        let mut req_param: BinanceReqParam = serde_json::from_value(self.config.req_param.clone())?;
+
+        for symbol in helpers::exchange_symbols(self, pairs) {
+            req_param.params.extend(self.stream_names(&symbol));
+        }
+        Ok(vec![serde_json::to_string(&req_param)?])
+    }
+
+    // dated futures end with the expiry, like `BTCUSDT_231229`
+    fn symbol(&self, base: &str, quote: &str) -> String {
+        let symbol = format!("{}{}", base.to_uppercase(), quote.to_uppercase());
+        match (self.config.market, &self.config.expiry) {
+            (MarketType::Futures, Some(expiry)) => format!("{}_{}", symbol, expiry),
+            _ => symbol,
+        }
+    }
+
+    fn normalise_symbol(&self, symbol: &str) -> String {
+        symbol.to_uppercase()
+    }
+
+    /// parse binance data from socket response
+    fn parse_response(&mut self, msg: Value) -> WSResult<ResponseEnum> {
+        // request result `{"result":null,"id":1}` or `{"error":{..},"id":1}` of the whole request
+        if let Some(id) = msg.get("id").and_then(Value::as_i64) {
+            // first subscribe has the id of config and every pair
+            let first = id == self.config.req_param["id"].as_i64().unwrap_or_default();
+            let symbols = self.subscribes.remove(&id);
+            if symbols.is_none() && !first {
+                return Ok(ResponseEnum::Ignore);
+            }
+            if let Some(error) = msg.get("error") {
+                let reason = error["msg"].as_str().unwrap_or_default().to_string();
+                return Ok(ResponseEnum::Ack(SubscribeAck::Rejected(
+                    symbols.unwrap_or_default(),
+                    reason,
+                )));
+            }
+            if msg.get("result").is_some_and(Value::is_null) {
+                return Ok(ResponseEnum::Ack(match symbols {
+                    Some(symbols) => SubscribeAck::Accepted(symbols),
+                    None => SubscribeAck::All,
+                }));
+            }
+        }
+
+        if msg["e"] == "markPriceUpdate" {
+            let mark_price: BinanceMarkPrice = match serde_json::from_value(msg) {
+                Ok(mark_price) => mark_price,
+                Err(_) => return Ok(ResponseEnum::Ignore),
+            };
+            return Ok(ResponseEnum::Derivatives(vec![
+                self.mark_price_update(mark_price)
+            ]));
+        }
+
+        if msg["e"] == "depthUpdate" {
+            let depth: BinanceDepth = match serde_json::from_value(msg) {
+                Ok(depth) => depth,
+                Err(_) => return Ok(ResponseEnum::Ignore),
+            };
+            return self.depth_update(depth);
+        }
+
+        if msg["e"] == "trade" || msg["e"] == "aggTrade" {
+            let trade: BinanceTrade = match serde_json::from_value(msg) {
+                Ok(trade) => trade,
+                Err(_) => return Ok(ResponseEnum::Ignore),
+            };
+            return Ok(ResponseEnum::Prices(vec![self.trade_update(trade)]));
+        }
+
+        // Serialize binance response, anything else is ignored
+        let binance_response: BinanceResponse = match serde_json::from_value(msg) {
+            Ok(p) => p,
+            Err(_) => return Ok(ResponseEnum::Ignore),
+        };
+        Ok(ResponseEnum::Prices(vec![PriceUpdate {
+            key: self.normalise_symbol(&binance_response.s),
+            ticker: Ticker {
+                last: binance_response.c,
+                bid: binance_response.b,
+                ask: binance_response.a,
+                bid_size: binance_response.bid_size,
+                ask_size: binance_response.ask_size,
+                volume_24h: binance_response.v,
+                event_time: binance_response.event_time,
+            },
+            received: None,
+            conversion: None,
+            trade: None,
+            market: None,
+        }]))
+    }
+}
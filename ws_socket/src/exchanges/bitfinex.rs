@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::Exchange;
+use crate::{
+    helpers,
+    types::{
+        BitfinexReqParam, BitfinexSubscribed, PriceUpdate, ResponseEnum, SubscribeAck, Ticker,
+        WSResult, WebSocketConfig,
+    },
+};
+
+/// bitfinex asset codes and the common code we use in pairs
+const ASSET_ALIASES: [(&str, &str); 2] = [("UST", "USDT"), ("UDC", "USDC")];
+
+/// position of last price in ticker array
+const LAST_PRICE: usize = 6;
+
+#[derive(Debug, Clone)]
+/// bitfinex v2 ticker adapter
+pub struct Bitfinex {
+    config: WebSocketConfig,
+    /// pairs cache key of every subscribed channel id on this connection
+    channels: HashMap<u64, String>,
+}
+
+impl Bitfinex {
+    pub fn new(config: &WebSocketConfig) -> Bitfinex {
+        Bitfinex {
+            config: config.clone(),
+            channels: HashMap::new(),
+        }
+    }
+
+    /// builder for exchange registry
+    pub fn build(config: &WebSocketConfig) -> Box<dyn Exchange> {
+        Box::new(Bitfinex::new(config))
+    }
+
+    /// bitfinex asset code of a common asset code
+    fn exchange_asset(code: &str) -> String {
+        let code = code.to_uppercase();
+        ASSET_ALIASES
+            .iter()
+            .find(|(_, asset)| *asset == code)
+            .map(|(bitfinex, _)| bitfinex.to_string())
+            .unwrap_or(code)
+    }
+
+    /// common asset code of a bitfinex asset code
+    fn asset(code: &str) -> String {
+        let code = code.to_uppercase();
+        ASSET_ALIASES
+            .iter()
+            .find(|(bitfinex, _)| *bitfinex == code)
+            .map(|(_, asset)| asset.to_string())
+            .unwrap_or(code)
+    }
+
+    /// price update from `[chanId, [BID, BID_SIZE, ASK, ASK_SIZE, ..., LAST_PRICE, ...]]`
+    fn parse_ticker(&self, frame: &[Value]) -> ResponseEnum {
+        let key = match frame[0].as_u64().and_then(|id| self.channels.get(&id)) {
+            Some(key) => key,
+            None => return ResponseEnum::Ignore,
+        };
+        // heartbeat frame is `[chanId, "hb"]`
+        match frame[1].get(LAST_PRICE) {
+            Some(Value::Number(price)) => ResponseEnum::Prices(vec![PriceUpdate {
+                key: key.clone(),
+                ticker: Ticker::new(price.to_string()),
+                received: None,
+                conversion: None,
+                trade: None,
+                market: None,
+            }]),
+            _ => ResponseEnum::Ignore,
+        }
+    }
+}
+
+impl Exchange for Bitfinex {
+    fn config(&self) -> &WebSocketConfig {
+        &self.config
+    }
+
+    fn req_param_fields(&self) -> &[&str] {
+        &["event", "channel"]
+    }
+
+    fn has_subscribe_ack(&self) -> bool {
+        true
+    }
+
+    // channel ids are given again after reconnect
+    fn reset(&mut self) {
+        self.channels.clear();
+    }
+
+    fn subscribe_params(&self, pairs: &[String]) -> WSResult<Vec<String>> {
+        let mut req_params = vec![];
+        for symbol in helpers::exchange_symbols(self, pairs) {
+            let mut req_param: BitfinexReqParam =
+                serde_json::from_value(self.config.req_param.clone())?;
+            req_param.symbol = symbol;
+            req_params.push(serde_json::to_string(&req_param)?);
+        }
+        Ok(req_params)
+    }
+
+    fn symbol(&self, base: &str, quote: &str) -> String {
+        let base = Bitfinex::exchange_asset(base);
+        let quote = Bitfinex::exchange_asset(quote);
+        // assets longer than three letters are separated by colon
+        if base.len() == 3 && quote.len() == 3 {
+            format!("t{}{}", base, quote)
+        } else {
+            format!("t{}:{}", base, quote)
+        }
+    }
+
+    fn normalise_symbol(&self, symbol: &str) -> String {
+        let symbol = symbol.strip_prefix('t').unwrap_or(symbol);
+        let (base, quote) = match symbol.split_once(':') {
+            Some(coin) => coin,
+            None if symbol.len() == 6 => symbol.split_at(3),
+            None => return symbol.to_uppercase(),
+        };
+        format!("{}{}", Bitfinex::asset(base), Bitfinex::asset(quote))
+    }
+
+    /// parse bitfinex data from socket response
+    fn parse_response(&mut self, msg: Value) -> WSResult<ResponseEnum> {
+        if let Value::Array(frame) = &msg {
+            if frame.len() < 2 {
+                return Ok(ResponseEnum::Ignore);
+            }
+            return Ok(self.parse_ticker(frame));
+        }
+
+        // subscribe error echoes the symbol of the request
+        if msg["event"] == "error" {
+            let symbols = msg["symbol"].as_str().map(str::to_string);
+            let reason = msg["msg"].as_str().unwrap_or_default().to_string();
+            return Ok(ResponseEnum::Ack(SubscribeAck::Rejected(
+                symbols.into_iter().collect(),
+                reason,
+            )));
+        }
+
+        // keep channel id of subscribed event, anything else is info event
+        if let Ok(subscribed) = serde_json::from_value::<BitfinexSubscribed>(msg) {
+            if subscribed.event == "subscribed" {
+                let key = self.normalise_symbol(&subscribed.symbol);
+                self.channels.insert(subscribed.chan_id, key);
+                return Ok(ResponseEnum::Ack(SubscribeAck::Accepted(vec![
+                    subscribed.symbol,
+                ])));
+            }
+        }
+        Ok(ResponseEnum::Ignore)
+    }
+}
@@ -0,0 +1,132 @@
+use serde_json::Value;
+
+use super::Exchange;
+use crate::{
+    helpers,
+    types::{
+        BybitReqParam, BybitResponse, Keepalive, PriceUpdate, ResponseEnum, SubscribeAck, Ticker,
+        WSResult, WebSocketConfig,
+    },
+};
+
+/// bybit accept at most 10 args in one spot subscribe request
+const MAX_ARGS: usize = 10;
+
+#[derive(Debug, Clone)]
+/// bybit v5 public spot tickers adapter
+pub struct Bybit {
+    config: WebSocketConfig,
+}
+
+impl Bybit {
+    pub fn new(config: &WebSocketConfig) -> Bybit {
+        Bybit {
+            config: config.clone(),
+        }
+    }
+
+    /// builder for exchange registry
+    pub fn build(config: &WebSocketConfig) -> Box<dyn Exchange> {
+        Box::new(Bybit::new(config))
+    }
+}
+
+impl Exchange for Bybit {
+    fn config(&self) -> &WebSocketConfig {
+        &self.config
+    }
+
+    fn req_param_fields(&self) -> &[&str] {
+        &["op", "args"]
+    }
+
+    fn subscribe_params(&self, pairs: &[String]) -> WSResult<Vec<String>> {
+        let symbols = helpers::exchange_symbols(self, pairs);
+
+        let mut req_params = vec![];
+        for chunk in symbols.chunks(MAX_ARGS) {
+            let mut req_param: BybitReqParam =
+                serde_json::from_value(self.config.req_param.clone())?;
+            req_param
+                .args
+                .extend(chunk.iter().map(|symbol| format!("tickers.{}", symbol)));
+            req_param.req_id = Some(chunk.join(","));
+            req_params.push(serde_json::to_string(&req_param)?);
+        }
+        Ok(req_params)
+    }
+
+    fn remove_params(&mut self, pairs: &[String]) -> WSResult<Vec<String>> {
+        let mut req_params = vec![];
+        for req_param in self.subscribe_params(pairs)? {
+            let mut req_param: BybitReqParam = serde_json::from_str(&req_param)?;
+            req_param.op = "unsubscribe".to_string();
+            req_params.push(serde_json::to_string(&req_param)?);
+        }
+        Ok(req_params)
+    }
+
+    fn has_subscribe_ack(&self) -> bool {
+        true
+    }
+
+    // bybit drop the connection without a ping every 20 seconds
+    fn default_keepalive(&self) -> Keepalive {
+        Keepalive::Text {
+            interval_ms: 20_000,
+            message: "{\"op\":\"ping\"}".to_string(),
+            pong: None,
+        }
+    }
+
+    fn symbol(&self, base: &str, quote: &str) -> String {
+        format!("{}{}", base.to_uppercase(), quote.to_uppercase())
+    }
+
+    fn normalise_symbol(&self, symbol: &str) -> String {
+        symbol.to_uppercase()
+    }
+
+    /// parse bybit data from socket response
+    fn parse_response(&mut self, msg: Value) -> WSResult<ResponseEnum> {
+        if msg["op"] == "subscribe" {
+            let symbols: Vec<String> = msg["req_id"]
+                .as_str()
+                .unwrap_or_default()
+                .split(',')
+                .filter(|symbol| !symbol.is_empty())
+                .map(str::to_string)
+                .collect();
+            if msg["success"] == true {
+                return Ok(ResponseEnum::Ack(SubscribeAck::Accepted(symbols)));
+            }
+            let reason = msg["ret_msg"].as_str().unwrap_or_default().to_string();
+            // reason lists the invalid topics, like `Invalid symbol :[tickers.XYZUSDT]`
+            let invalid: Vec<String> = symbols
+                .iter()
+                .filter(|symbol| reason.contains(&format!("tickers.{}", symbol)))
+                .cloned()
+                .collect();
+            let symbols = if invalid.is_empty() { symbols } else { invalid };
+            return Ok(ResponseEnum::Ack(SubscribeAck::Rejected(symbols, reason)));
+        }
+        if msg["success"] == false {
+            let reason = msg["ret_msg"].as_str().unwrap_or_default().to_string();
+            return Ok(ResponseEnum::Ack(SubscribeAck::Rejected(vec![], reason)));
+        }
+
+        // Serialize bybit response, anything else is subscribe result or pong
+        let bybit_response: BybitResponse = match serde_json::from_value(msg) {
+            Ok(p) => p,
+            Err(_) => return Ok(ResponseEnum::Ignore),
+        };
+        Ok(ResponseEnum::Prices(vec![PriceUpdate {
+            key: self.normalise_symbol(&bybit_response.data.symbol),
+            ticker: Ticker::new(bybit_response.data.last_price),
+            received: None,
+            conversion: None,
+            trade: None,
+            market: None,
+        }]))
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::Exchange;
+use crate::{
+    book::{BookSide, OrderBook},
+    errors::WSError,
+    helpers,
+    types::{
+        CoinbaseL2Update, CoinbaseMatch, CoinbaseReqParam, CoinbaseResponse, CoinbaseSnapshot,
+        Keepalive, PriceUpdate, ResponseEnum, StreamKind, SubscribeAck, Ticker, Trade, TradeSide,
+        WSResult, WebSocketConfig,
+    },
+};
+
+#[derive(Debug, Clone)]
+/// coinbase ticker channel adapter
+pub struct Coinbase {
+    config: WebSocketConfig,
+    /// order book of each product of the level2 channel
+    books: HashMap<String, OrderBook>,
+}
+
+impl Coinbase {
+    pub fn new(config: &WebSocketConfig) -> Coinbase {
+        Coinbase {
+            config: config.clone(),
+            books: HashMap::new(),
+        }
+    }
+
+    /// channel of the configured stream kind
+    fn channel(&self) -> &str {
+        match self.config.stream {
+            StreamKind::Trades => "matches",
+            StreamKind::Book => "level2",
+            _ => "ticker",
+        }
+    }
+
+    /// price of the book top of the product
+    fn book_prices(&self, product_id: &str, event_time: Option<u64>) -> ResponseEnum {
+        let update = self.books.get(product_id).and_then(|book| {
+            book.price_update(
+                self.normalise_symbol(product_id),
+                self.config.book_price,
+                event_time,
+            )
+        });
+        ResponseEnum::Prices(update.into_iter().collect())
+    }
+
+    /// apply the changes on the book of the product, a product without book is loaded again
+    fn l2_update(&mut self, update: CoinbaseL2Update) -> WSResult<ResponseEnum> {
+        let product_id = update.product_id;
+        let book = match self.books.get_mut(&product_id) {
+            Some(book) => book,
+            None => return Ok(ResponseEnum::Resync(product_id)),
+        };
+        for (side, price, size) in &update.changes {
+            let side = if side == "buy" {
+                BookSide::Bid
+            } else {
+                BookSide::Ask
+            };
+            book.apply(side, price, size)?;
+        }
+        // level2 has no sequence, a crossed book is the only sign of a missed change
+        if book.is_crossed() {
+            self.books.remove(&product_id);
+            return Ok(ResponseEnum::Resync(product_id));
+        }
+        let event_time = update.time.as_deref().and_then(helpers::rfc3339_millis);
+        Ok(self.book_prices(&product_id, event_time))
+    }
+
+    /// builder for exchange registry
+    pub fn build(config: &WebSocketConfig) -> Box<dyn Exchange> {
+        Box::new(Coinbase::new(config))
+    }
+}
+
+impl Exchange for Coinbase {
+    fn config(&self) -> &WebSocketConfig {
+        &self.config
+    }
+
+    fn req_param_fields(&self) -> &[&str] {
+        &["type", "channels", "product_ids"]
+    }
+
+    fn remove_params(&mut self, pairs: &[String]) -> WSResult<Vec<String>> {
+        let mut req_params = vec![];
+        for req_param in self.subscribe_params(pairs)? {
+            let mut req_param: CoinbaseReqParam = serde_json::from_str(&req_param)?;
+            req_param.type_name = "unsubscribe".to_string();
+            req_params.push(serde_json::to_string(&req_param)?);
+        }
+        Ok(req_params)
+    }
+
+    fn has_subscribe_ack(&self) -> bool {
+        true
+    }
+
+    fn rejects_whole_request(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        self.books.clear();
+    }
+
+    // heartbeat channel send a message every second for each product
+    fn default_keepalive(&self) -> Keepalive {
+        Keepalive::Channel
+    }
+
+    fn streams(&self) -> &[StreamKind] {
+        &[StreamKind::Ticker, StreamKind::Trades, StreamKind::Book]
+    }
+
+    fn subscribe_params(&self, pairs: &[String]) -> WSResult<Vec<String>> {
+        let mut req_param: CoinbaseReqParam =
+            serde_json::from_value(self.config.req_param.clone())?;
+
+        req_param
+            .product_ids
+            .extend(helpers::exchange_symbols(self, pairs));
+        if self.channel() != "ticker" {
+            req_param.channels.retain(|channel| channel != "ticker");
+            req_param.channels.push(self.channel().to_string());
+        }
+        if self.keepalive() == Keepalive::Channel
+            && !req_param
+                .channels
+                .iter()
+                .any(|channel| channel == "heartbeat")
+        {
+            req_param.channels.push("heartbeat".to_string());
+        }
+        Ok(vec![serde_json::to_string(&req_param)?])
+    }
+
+    fn symbol(&self, base: &str, quote: &str) -> String {
+        format!("{}-{}", base.to_uppercase(), quote.to_uppercase())
+    }
+
+    fn normalise_symbol(&self, symbol: &str) -> String {
+        helpers::pair_key(symbol)
+    }
+
+    /// parse coinbase data from socket response
+    fn parse_response(&mut self, msg: Value) -> WSResult<ResponseEnum> {
+        if msg["type"] == "error" {
+            // reason is like "BTC-ABC is not a valid product"
+            if msg["message"] == "Failed to subscribe" {
+                let reason = msg["reason"].as_str().unwrap_or_default().to_string();
+                let symbols = reason
+                    .strip_suffix(" is not a valid product")
+                    .map(str::to_string);
+                return Ok(ResponseEnum::Ack(SubscribeAck::Rejected(
+                    symbols.into_iter().collect(),
+                    reason,
+                )));
+            }
+            let error = format!("{:?}", msg);
+            return Err(WSError::SocketResponseError(error));
+        }
+        if msg["type"] == "subscriptions" {
+            let symbols = msg["channels"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .flat_map(|channel| channel["product_ids"].as_array().into_iter().flatten())
+                .filter_map(|product_id| product_id.as_str().map(str::to_string))
+                .collect();
+            return Ok(ResponseEnum::Ack(SubscribeAck::Accepted(symbols)));
+        }
+
+        if msg["type"] == "snapshot" {
+            let snapshot: CoinbaseSnapshot = match serde_json::from_value(msg) {
+                Ok(snapshot) => snapshot,
+                Err(_) => return Ok(ResponseEnum::Ignore),
+            };
+            let book = OrderBook::from_levels(&snapshot.bids, &snapshot.asks)?;
+            self.books.insert(snapshot.product_id.clone(), book);
+            return Ok(self.book_prices(&snapshot.product_id, None));
+        }
+        if msg["type"] == "l2update" {
+            let update: CoinbaseL2Update = match serde_json::from_value(msg) {
+                Ok(update) => update,
+                Err(_) => return Ok(ResponseEnum::Ignore),
+            };
+            return self.l2_update(update);
+        }
+
+        // last match is sent once after subscribe
+        if msg["type"] == "match" || msg["type"] == "last_match" {
+            let trade: CoinbaseMatch = match serde_json::from_value(msg) {
+                Ok(trade) => trade,
+                Err(_) => return Ok(ResponseEnum::Ignore),
+            };
+            // side is of the maker order, the taker took the other side
+            let side = match trade.side {
+                TradeSide::Buy => TradeSide::Sell,
+                TradeSide::Sell => TradeSide::Buy,
+            };
+            return Ok(ResponseEnum::Prices(vec![PriceUpdate {
+                key: self.normalise_symbol(&trade.product_id),
+                ticker: Ticker {
+                    event_time: trade.time.as_deref().and_then(helpers::rfc3339_millis),
+                    ..Ticker::new(trade.price.clone())
+                },
+                received: None,
+                conversion: None,
+                trade: Some(Trade {
+                    id: trade.trade_id.to_string(),
+                    price: trade.price,
+                    size: trade.size,
+                    side,
+                }),
+                market: None,
+            }]));
+        }
+
+        // Serialize coinbase response, anything else is ignored
+        let coinbase_response: CoinbaseResponse = match serde_json::from_value(msg) {
+            Ok(p) => p,
+            Err(_) => return Ok(ResponseEnum::Ignore),
+        };
+        Ok(ResponseEnum::Prices(vec![PriceUpdate {
+            key: self.normalise_symbol(&coinbase_response.product_id),
+            ticker: Ticker {
+                last: coinbase_response.price,
+                bid: coinbase_response.best_bid,
+                ask: coinbase_response.best_ask,
+                bid_size: coinbase_response.best_bid_size,
+                ask_size: coinbase_response.best_ask_size,
+                volume_24h: coinbase_response.volume_24h,
+                event_time: coinbase_response
+                    .time
+                    .as_deref()
+                    .and_then(helpers::rfc3339_millis),
+            },
+            received: None,
+            conversion: None,
+            trade: None,
+            market: None,
+        }]))
+    }
+}
@@ -0,0 +1,204 @@
+use serde_json::Value;
+
+use super::Exchange;
+use crate::{
+    errors::WSError,
+    helpers,
+    types::{
+        GenericConfig, PriceUpdate, ResponseEnum, SymbolCase, Ticker, WSResult, WebSocketConfig,
+    },
+};
+
+#[derive(Debug, Clone)]
+/// exchange adapter driven by the `generic` field mapping of its socket config
+pub struct Generic {
+    config: WebSocketConfig,
+}
+
+impl Generic {
+    pub fn new(config: &WebSocketConfig) -> Generic {
+        Generic {
+            config: config.clone(),
+        }
+    }
+
+    /// builder for exchange registry
+    pub fn build(config: &WebSocketConfig) -> Box<dyn Exchange> {
+        Box::new(Generic::new(config))
+    }
+
+    fn mapping(&self) -> Option<&GenericConfig> {
+        self.config.generic.as_ref()
+    }
+
+    fn invalid(&self, reason: &str) -> WSError {
+        WSError::InvalidGenericConfig(self.config.name.clone(), reason.to_string())
+    }
+
+    /// price update of one ticker object
+    fn price_update(&self, mapping: &GenericConfig, ticker: &Value) -> Option<PriceUpdate> {
+        let symbol = ticker.pointer(&mapping.symbol_path)?.as_str()?;
+        let price = helpers::price_text(ticker.pointer(&mapping.price_path)?)?;
+        Some(PriceUpdate {
+            key: self.normalise_symbol(symbol),
+            ticker: Ticker::new(price),
+            received: None,
+            conversion: None,
+            trade: None,
+            market: None,
+        })
+    }
+}
+
+/// replace `{symbol}` in every string of the template
+fn render_template(template: &Value, symbol: &str) -> Value {
+    match template {
+        Value::String(s) => Value::String(s.replace("{symbol}", symbol)),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| render_template(item, symbol))
+                .collect(),
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), render_template(value, symbol)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// json pointer must be empty or start with `/`
+fn is_pointer(path: &str) -> bool {
+    path.is_empty() || path.starts_with('/')
+}
+
+impl Exchange for Generic {
+    fn config(&self) -> &WebSocketConfig {
+        &self.config
+    }
+
+    fn validate(&self) -> WSResult<()> {
+        let mapping = self
+            .mapping()
+            .ok_or_else(|| self.invalid("missing mapping"))?;
+
+        if !self
+            .config
+            .req_param
+            .pointer(&mapping.pairs_path)
+            .is_some_and(Value::is_array)
+        {
+            return Err(WSError::MissingReqParam(
+                self.config.name.clone(),
+                mapping.pairs_path.clone(),
+            ));
+        }
+
+        for path in [
+            &mapping.data_path,
+            &mapping.symbol_path,
+            &mapping.price_path,
+        ] {
+            if !is_pointer(path) {
+                return Err(self.invalid(&format!("{} is not a json pointer", path)));
+            }
+        }
+        if let Some(error) = &mapping.error {
+            if !is_pointer(&error.path) {
+                return Err(self.invalid(&format!("{} is not a json pointer", error.path)));
+            }
+        }
+        Ok(())
+    }
+
+    fn subscribe_params(&self, pairs: &[String]) -> WSResult<Vec<String>> {
+        let mapping = self
+            .mapping()
+            .ok_or_else(|| self.invalid("missing mapping"))?;
+
+        let mut req_param = self.config.req_param.clone();
+        let params = req_param
+            .pointer_mut(&mapping.pairs_path)
+            .and_then(Value::as_array_mut)
+            .ok_or_else(|| {
+                WSError::MissingReqParam(self.config.name.clone(), mapping.pairs_path.clone())
+            })?;
+
+        for symbol in helpers::exchange_symbols(self, pairs) {
+            params.push(render_template(&mapping.pair_template, &symbol));
+        }
+        Ok(vec![serde_json::to_string(&req_param)?])
+    }
+
+    fn symbol(&self, base: &str, quote: &str) -> String {
+        let format = self.mapping().map(|mapping| &mapping.symbol);
+        let (prefix, separator, case) = match format {
+            Some(format) => (
+                format.prefix.as_str(),
+                format.separator.as_str(),
+                format.case,
+            ),
+            None => ("", "", SymbolCase::Upper),
+        };
+        let symbol = format!("{}{}{}", base, separator, quote);
+        match case {
+            SymbolCase::Upper => format!("{}{}", prefix, symbol.to_uppercase()),
+            SymbolCase::Lower => format!("{}{}", prefix, symbol.to_lowercase()),
+        }
+    }
+
+    fn normalise_symbol(&self, symbol: &str) -> String {
+        let (prefix, separator) = match self.mapping() {
+            Some(mapping) => (
+                mapping.symbol.prefix.as_str(),
+                mapping.symbol.separator.as_str(),
+            ),
+            None => ("", ""),
+        };
+        let symbol = symbol.strip_prefix(prefix).unwrap_or(symbol);
+        let symbol = if separator.is_empty() {
+            symbol.to_string()
+        } else {
+            symbol.replace(separator, "")
+        };
+        symbol.to_uppercase()
+    }
+
+    /// parse data from socket response with the field mapping
+    fn parse_response(&mut self, msg: Value) -> WSResult<ResponseEnum> {
+        let mapping = match self.mapping() {
+            Some(mapping) => mapping,
+            None => return Ok(ResponseEnum::Ignore),
+        };
+
+        if let Some(rule) = &mapping.error {
+            let is_error = match (msg.pointer(&rule.path), &rule.equals) {
+                (Some(value), Some(equals)) => value == equals,
+                (Some(value), None) => !value.is_null(),
+                (None, _) => false,
+            };
+            if is_error {
+                let error = format!("{:?}", msg);
+                return Err(WSError::SocketResponseError(error));
+            }
+        }
+
+        let prices: Vec<PriceUpdate> = match msg.pointer(&mapping.data_path) {
+            Some(Value::Array(tickers)) => tickers
+                .iter()
+                .filter_map(|ticker| self.price_update(mapping, ticker))
+                .collect(),
+            Some(ticker) => self.price_update(mapping, ticker).into_iter().collect(),
+            None => vec![],
+        };
+
+        if prices.is_empty() {
+            Ok(ResponseEnum::Ignore)
+        } else {
+            Ok(ResponseEnum::Prices(prices))
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use serde_json::{json, Value};
+
+use super::Exchange;
+use crate::{
+    helpers,
+    types::{
+        HtxReqParam, PriceUpdate, ResponseEnum, SubscribeAck, Ticker, WSResult, WebSocketConfig,
+    },
+};
+
+#[derive(Debug, Clone)]
+/// htx (huobi) market ticker adapter, frames are gzip compressed
+pub struct Htx {
+    config: WebSocketConfig,
+}
+
+impl Htx {
+    pub fn new(config: &WebSocketConfig) -> Htx {
+        Htx {
+            config: config.clone(),
+        }
+    }
+
+    /// builder for exchange registry
+    pub fn build(config: &WebSocketConfig) -> Box<dyn Exchange> {
+        Box::new(Htx::new(config))
+    }
+}
+
+/// symbol of a topic like `market.btcusdt.ticker` in the text
+fn topic_symbol(text: &str) -> Option<String> {
+    let symbol = text.split("market.").nth(1)?.split('.').next()?;
+    Some(symbol.to_string()).filter(|symbol| !symbol.is_empty())
+}
+
+impl Exchange for Htx {
+    fn config(&self) -> &WebSocketConfig {
+        &self.config
+    }
+
+    fn req_param_fields(&self) -> &[&str] {
+        &["id"]
+    }
+
+    fn subscribe_params(&self, pairs: &[String]) -> WSResult<Vec<String>> {
+        let mut req_params = vec![];
+        for symbol in helpers::exchange_symbols(self, pairs) {
+            let mut req_param: HtxReqParam = serde_json::from_value(self.config.req_param.clone())?;
+            req_param.sub = format!("market.{}.ticker", symbol);
+            req_params.push(serde_json::to_string(&req_param)?);
+        }
+        Ok(req_params)
+    }
+
+    fn has_subscribe_ack(&self) -> bool {
+        true
+    }
+
+    fn symbol(&self, base: &str, quote: &str) -> String {
+        format!("{}{}", base.to_lowercase(), quote.to_lowercase())
+    }
+
+    fn normalise_symbol(&self, symbol: &str) -> String {
+        symbol.to_uppercase()
+    }
+
+    /// parse htx data from socket response
+    fn parse_response(&mut self, msg: Value) -> WSResult<ResponseEnum> {
+        // error is like {"status":"error","err-msg":"invalid topic market.abcusdt.ticker"}
+        if msg["status"] == "error" {
+            let reason = msg["err-msg"].as_str().unwrap_or_default().to_string();
+            let symbols = topic_symbol(&reason).into_iter().collect();
+            return Ok(ResponseEnum::Ack(SubscribeAck::Rejected(symbols, reason)));
+        }
+        if let Some(subbed) = msg["subbed"].as_str().filter(|_| msg["status"] == "ok") {
+            let symbols = topic_symbol(subbed).into_iter().collect();
+            return Ok(ResponseEnum::Ack(SubscribeAck::Accepted(symbols)));
+        }
+
+        // htx close the socket unless every ping is answered with the same number
+        if let Some(ping) = msg.get("ping") {
+            return Ok(ResponseEnum::Reply(json!({ "pong": ping }).to_string()));
+        }
+
+        // ticker has channel and last price, anything else is subscribe result
+        let (channel, price) = match (
+            msg["ch"].as_str(),
+            helpers::price_text(&msg["tick"]["lastPrice"]),
+        ) {
+            (Some(channel), Some(price)) => (channel, price),
+            _ => return Ok(ResponseEnum::Ignore),
+        };
+        // channel is `market.btcusdt.ticker`
+        let symbol = channel.split('.').nth(1).unwrap_or_default();
+        Ok(ResponseEnum::Prices(vec![PriceUpdate {
+            key: self.normalise_symbol(symbol),
+            ticker: Ticker::new(price),
+            received: None,
+            conversion: None,
+            trade: None,
+            market: None,
+        }]))
+    }
+}
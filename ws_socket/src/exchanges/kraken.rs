@@ -0,0 +1,126 @@
+use serde_json::Value;
+
+use super::Exchange;
+use crate::{
+    helpers,
+    types::{
+        KrakenReqParam, PriceUpdate, ResponseEnum, SubscribeAck, Ticker, WSResult, WebSocketConfig,
+    },
+};
+
+/// kraken legacy asset codes and the common code we use in pairs
+const ASSET_ALIASES: [(&str, &str); 2] = [("XBT", "BTC"), ("XDG", "DOGE")];
+
+#[derive(Debug, Clone)]
+/// kraken websocket v2 ticker adapter
+pub struct Kraken {
+    config: WebSocketConfig,
+}
+
+impl Kraken {
+    pub fn new(config: &WebSocketConfig) -> Kraken {
+        Kraken {
+            config: config.clone(),
+        }
+    }
+
+    /// builder for exchange registry
+    pub fn build(config: &WebSocketConfig) -> Box<dyn Exchange> {
+        Box::new(Kraken::new(config))
+    }
+
+    /// common asset code of a kraken asset code
+    fn asset(code: &str) -> String {
+        let code = code.to_uppercase();
+        ASSET_ALIASES
+            .iter()
+            .find(|(kraken, _)| *kraken == code)
+            .map(|(_, asset)| asset.to_string())
+            .unwrap_or(code)
+    }
+}
+
+impl Exchange for Kraken {
+    fn config(&self) -> &WebSocketConfig {
+        &self.config
+    }
+
+    fn req_param_fields(&self) -> &[&str] {
+        &["method", "params"]
+    }
+
+    fn subscribe_params(&self, pairs: &[String]) -> WSResult<Vec<String>> {
+        let mut req_param: KrakenReqParam = serde_json::from_value(self.config.req_param.clone())?;
+
+        req_param
+            .params
+            .symbol
+            .extend(helpers::exchange_symbols(self, pairs));
+        Ok(vec![serde_json::to_string(&req_param)?])
+    }
+
+    fn remove_params(&mut self, pairs: &[String]) -> WSResult<Vec<String>> {
+        let mut req_params = vec![];
+        for req_param in self.subscribe_params(pairs)? {
+            let mut req_param: KrakenReqParam = serde_json::from_str(&req_param)?;
+            req_param.method = "unsubscribe".to_string();
+            req_params.push(serde_json::to_string(&req_param)?);
+        }
+        Ok(req_params)
+    }
+
+    fn has_subscribe_ack(&self) -> bool {
+        true
+    }
+
+    // v2 api takes common asset codes, so only the separator differs
+    fn symbol(&self, base: &str, quote: &str) -> String {
+        format!("{}/{}", Kraken::asset(base), Kraken::asset(quote))
+    }
+
+    fn normalise_symbol(&self, symbol: &str) -> String {
+        symbol.split('/').map(Kraken::asset).collect()
+    }
+
+    /// parse kraken data from socket response
+    fn parse_response(&mut self, msg: Value) -> WSResult<ResponseEnum> {
+        // error names the rejected symbol, the reason names it too
+        if msg["success"] == false || msg["error"].is_string() {
+            let symbols = msg["symbol"].as_str().map(str::to_string);
+            let reason = msg["error"].as_str().unwrap_or_default().to_string();
+            return Ok(ResponseEnum::Ack(SubscribeAck::Rejected(
+                symbols.into_iter().collect(),
+                reason,
+            )));
+        }
+        if msg["method"] == "subscribe" {
+            let symbols = msg["result"]["symbol"].as_str().map(str::to_string);
+            return Ok(ResponseEnum::Ack(SubscribeAck::Accepted(
+                symbols.into_iter().collect(),
+            )));
+        }
+        if msg["channel"] != "ticker" {
+            return Ok(ResponseEnum::Ignore);
+        }
+
+        // prices are numbers, they are read from the value to keep their digits
+        let data = match msg["data"].as_array() {
+            Some(data) => data,
+            None => return Ok(ResponseEnum::Ignore),
+        };
+        let prices = data
+            .iter()
+            .filter_map(|data| {
+                Some(PriceUpdate {
+                    key: self.normalise_symbol(data["symbol"].as_str()?),
+                    ticker: Ticker::new(helpers::price_text(&data["last"])?),
+                    received: None,
+                    conversion: None,
+                    trade: None,
+                    market: None,
+                })
+            })
+            .collect();
+        Ok(ResponseEnum::Prices(prices))
+    }
+}
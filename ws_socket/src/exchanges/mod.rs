@@ -0,0 +1,245 @@
+use std::fmt::Debug;
+
+use serde_json::Value;
+use url::Url;
+
+use crate::errors::WSError;
+use crate::types::{
+    Keepalive, MarketType, Pair, ResponseEnum, StreamKind, WSDetails, WSResult, WebSocketConfig,
+};
+
+mod binance;
+mod bitfinex;
+mod bybit;
+mod coinbase;
+mod generic;
+mod htx;
+mod kraken;
+mod okx;
+
+pub use binance::Binance;
+pub use bitfinex::Bitfinex;
+pub use bybit::Bybit;
+pub use coinbase::Coinbase;
+pub use generic::Generic;
+pub use htx::Htx;
+pub use kraken::Kraken;
+pub use okx::Okx;
+
+/// Exchange adapter, implement it and register it in `ExchangeRegistry` to add a venue
+pub trait Exchange: Debug + Send {
+    /// socket config of the exchange
+    fn config(&self) -> &WebSocketConfig;
+
+    /// exchange name, used to tag the data points
+    fn name(&self) -> &str {
+        &self.config().name
+    }
+
+    /// web socket url for the pairs
+    fn ws_url(&self, _pairs: &[String]) -> WSResult<Url> {
+        Ok(Url::parse(&self.config().ws_base_url)?)
+    }
+
+    /// fields the req_param template must have
+    fn req_param_fields(&self) -> &[&str] {
+        &[]
+    }
+
+    /// extra config checks of the adapter, run when ws_details.json is loaded
+    fn validate(&self) -> WSResult<()> {
+        Ok(())
+    }
+
+    /// clear connection state before the socket is reconnected
+    fn reset(&mut self) {}
+
+    /// subscription messages sent after the socket is connected
+    fn subscribe_params(&self, pairs: &[String]) -> WSResult<Vec<String>>;
+
+    /// subscribe requests for pairs added to a connected socket
+    fn add_params(&mut self, pairs: &[String]) -> WSResult<Vec<String>> {
+        self.subscribe_params(pairs)
+    }
+
+    /// unsubscribe requests for pairs removed from a connected socket
+    fn remove_params(&mut self, _pairs: &[String]) -> WSResult<Vec<String>> {
+        Err(WSError::UnsubscribeNotSupported(self.name().to_string()))
+    }
+
+    /// market data streams the adapter can subscribe
+    fn streams(&self) -> &[StreamKind] {
+        &[StreamKind::Ticker]
+    }
+
+    /// markets the adapter can map pairs to
+    fn markets(&self) -> &[MarketType] {
+        &[MarketType::Spot]
+    }
+
+    /// exchange answer every subscribe request with an ack
+    fn has_subscribe_ack(&self) -> bool {
+        false
+    }
+
+    /// one rejected symbol fails the whole subscribe request, the other pending pairs are sent again
+    fn rejects_whole_request(&self) -> bool {
+        false
+    }
+
+    /// keepalive the exchange needs when config does not set one
+    fn default_keepalive(&self) -> Keepalive {
+        Keepalive::None
+    }
+
+    /// keepalive of the socket, config first then adapter default
+    fn keepalive(&self) -> Keepalive {
+        match &self.config().keepalive {
+            Some(keepalive) => keepalive.clone(),
+            None => self.default_keepalive(),
+        }
+    }
+
+    /// most pairs one connection of the exchange can carry when config does not set it
+    fn default_max_pairs(&self) -> Option<usize> {
+        None
+    }
+
+    /// most pairs of one connection, config first then adapter default
+    fn max_pairs_per_connection(&self) -> Option<usize> {
+        self.config()
+            .max_pairs_per_connection
+            .or_else(|| self.default_max_pairs())
+    }
+
+    /// exchange symbol for base and quote coin
+    fn symbol(&self, base: &str, quote: &str) -> String;
+
+    /// exchange symbol of the pair, symbol map of config first then `symbol` with venue assets
+    fn pair_symbol(&self, pair: &Pair) -> String {
+        let symbols = &self.config().symbols;
+        match symbols.pair(pair) {
+            Some(symbol) => symbol.to_string(),
+            None => self.symbol(symbols.asset(&pair.base), symbols.asset(&pair.quote)),
+        }
+    }
+
+    /// rest url of the order book snapshot of the exchange symbol, none when the socket sends snapshots
+    ///
+    /// A book without snapshot url is loaded again by reconnecting the socket.
+    fn snapshot_url(&self, _symbol: &str) -> WSResult<Option<Url>> {
+        Ok(None)
+    }
+
+    /// load the order book snapshot of the exchange symbol and apply the updates read before it
+    fn apply_snapshot(&mut self, _symbol: &str, _snapshot: Value) -> WSResult<ResponseEnum> {
+        Ok(ResponseEnum::Ignore)
+    }
+
+    /// pairs cache key for exchange symbol
+    fn normalise_symbol(&self, symbol: &str) -> String;
+
+    /// parse json socket response
+    fn parse_response(&mut self, msg: Value) -> WSResult<ResponseEnum>;
+}
+
+/// registered name and market of a config name, `okx_swap` is the okx adapter on the swap market
+pub fn split_market(name: &str) -> (&str, Option<MarketType>) {
+    for market in [MarketType::Spot, MarketType::Swap, MarketType::Futures] {
+        if let Some(base) = name.strip_suffix(&format!("_{}", market)) {
+            return (base, Some(market));
+        }
+    }
+    (name, None)
+}
+
+/// create exchange adapter from socket config
+pub type ExchangeBuilder = fn(&WebSocketConfig) -> Box<dyn Exchange>;
+
+#[derive(Debug, Clone)]
+/// Exchange registry structure, keep exchange builders by name
+pub struct ExchangeRegistry {
+    builders: Vec<(String, ExchangeBuilder)>,
+}
+
+impl ExchangeRegistry {
+    /// registry without any exchange
+    pub fn new() -> ExchangeRegistry {
+        ExchangeRegistry { builders: vec![] }
+    }
+
+    /// register exchange builder, replace the builder if name is already registered
+    pub fn register(&mut self, name: &str, builder: ExchangeBuilder) {
+        match self.builders.iter_mut().find(|(n, _)| n == name) {
+            Some(entry) => entry.1 = builder,
+            None => self.builders.push((name.to_string(), builder)),
+        }
+    }
+
+    /// get exchange builder by name, a name with market suffix gets the builder without it
+    pub fn get(&self, name: &str) -> Option<ExchangeBuilder> {
+        let find = |name: &str| {
+            self.builders
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, builder)| *builder)
+        };
+        find(name).or_else(|| find(split_market(name).0))
+    }
+
+    /// get exchange builder for socket config, config with generic mapping always use `Generic`
+    pub fn builder(&self, config: &WebSocketConfig) -> Option<ExchangeBuilder> {
+        if config.generic.is_some() {
+            return Some(Generic::build);
+        }
+        self.get(&config.name)
+    }
+
+    /// registered exchange names in registration order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.builders.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// create adapters for registered exchanges which have socket config, then for generic configs
+    ///
+    /// Configs of other markets, like `okx_swap`, follow the config of the exchange.
+    pub fn build(&self, ws_details: &WSDetails) -> Vec<Box<dyn Exchange>> {
+        let mut exchanges = vec![];
+        for name in self.names() {
+            let mut configs: Vec<&WebSocketConfig> = ws_details
+                .values()
+                .filter(|config| config.name == name || split_market(&config.name).0 == name)
+                .collect();
+            if configs.is_empty() {
+                println!("Exchange: {} has no socket config", name);
+            }
+            configs.sort_by(|a, b| a.name.cmp(&b.name));
+            for config in configs {
+                exchanges.extend(self.builder(config).map(|build| build(config)));
+            }
+        }
+
+        let mut generic: Vec<&WebSocketConfig> = ws_details
+            .values()
+            .filter(|config| config.generic.is_some() && self.get(&config.name).is_none())
+            .collect();
+        generic.sort_by(|a, b| a.name.cmp(&b.name));
+        exchanges.extend(generic.into_iter().map(Generic::build));
+        exchanges
+    }
+}
+
+impl Default for ExchangeRegistry {
+    /// registry with binance, coinbase, okx, kraken, bybit, bitfinex and htx
+    fn default() -> Self {
+        let mut registry = ExchangeRegistry::new();
+        registry.register("binance", Binance::build);
+        registry.register("coinbase", Coinbase::build);
+        registry.register("okx", Okx::build);
+        registry.register("kraken", Kraken::build);
+        registry.register("bybit", Bybit::build);
+        registry.register("bitfinex", Bitfinex::build);
+        registry.register("htx", Htx::build);
+        registry
+    }
+}
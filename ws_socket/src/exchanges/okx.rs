@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::Exchange;
+use crate::{
+    book::{BookSide, OrderBook},
+    helpers,
+    types::{
+        DerivativeUpdate, Keepalive, MarketType, OkexBooks, OkexData, OkexFundingRate,
+        OkexMarkPrice, OkexReqParam, OkexReqParamArg, OkexResponse, OkexTrades, PriceUpdate,
+        ResponseEnum, StreamKind, SubscribeAck, Ticker, Trade, WSResult, WebSocketConfig,
+    },
+};
+
+#[derive(Debug, Clone, Default)]
+/// local book of the books channel with the sequence id of its last update
+struct SeqBook {
+    book: OrderBook,
+    seq_id: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+/// okx tickers channel adapter
+pub struct Okx {
+    config: WebSocketConfig,
+    /// order book of each instrument of the books channel
+    books: HashMap<String, SeqBook>,
+}
+
+impl Okx {
+    pub fn new(config: &WebSocketConfig) -> Okx {
+        Okx {
+            config: config.clone(),
+            books: HashMap::new(),
+        }
+    }
+
+    /// channel of the configured stream kind
+    fn channel(&self) -> &str {
+        match self.config.stream {
+            StreamKind::Trades => "trades",
+            StreamKind::Book => "books",
+            _ => "tickers",
+        }
+    }
+
+    /// mark price and funding rate channels of the market, none for spot
+    fn derivative_channels(&self) -> &[&str] {
+        if !self.config.mark_funding {
+            return &[];
+        }
+        match self.config.market {
+            MarketType::Spot => &[],
+            MarketType::Swap => &["mark-price", "funding-rate"],
+            MarketType::Futures => &["mark-price"],
+        }
+    }
+
+    /// apply snapshot or update on the book of the instrument, then check sequence and checksum
+    fn books_update(&mut self, books: OkexBooks) -> WSResult<ResponseEnum> {
+        let inst_id = books.arg.inst_id;
+        let mut event_time = None;
+        for data in books.data {
+            if books.action == "snapshot" {
+                self.books.insert(inst_id.clone(), SeqBook::default());
+            }
+            let book = match self.books.get_mut(&inst_id) {
+                Some(book) => book,
+                None => return Ok(ResponseEnum::Resync(inst_id)),
+            };
+            // update follows the last one, snapshot has no previous
+            if books.action == "update" && data.prev_seq_id != book.seq_id {
+                self.books.remove(&inst_id);
+                return Ok(ResponseEnum::Resync(inst_id));
+            }
+            for (price, size, _, _) in &data.bids {
+                book.book.apply(BookSide::Bid, price, size)?;
+            }
+            for (price, size, _, _) in &data.asks {
+                book.book.apply(BookSide::Ask, price, size)?;
+            }
+            book.seq_id = data.seq_id;
+            if book.book.okx_checksum() != data.checksum || book.book.is_crossed() {
+                self.books.remove(&inst_id);
+                return Ok(ResponseEnum::Resync(inst_id));
+            }
+            event_time = data.ts.parse().ok();
+        }
+
+        let update = self.books.get(&inst_id).and_then(|book| {
+            book.book.price_update(
+                self.normalise_symbol(&inst_id),
+                self.config.book_price,
+                event_time,
+            )
+        });
+        Ok(ResponseEnum::Prices(update.into_iter().collect()))
+    }
+
+    /// builder for exchange registry
+    pub fn build(config: &WebSocketConfig) -> Box<dyn Exchange> {
+        Box::new(Okx::new(config))
+    }
+}
+
+impl Exchange for Okx {
+    fn config(&self) -> &WebSocketConfig {
+        &self.config
+    }
+
+    fn req_param_fields(&self) -> &[&str] {
+        &["op", "args"]
+    }
+
+    // okx close the socket after 30 seconds without data, answer of "ping" is plain "pong"
+    fn default_keepalive(&self) -> Keepalive {
+        Keepalive::Text {
+            interval_ms: 25_000,
+            message: "ping".to_string(),
+            pong: Some("pong".to_string()),
+        }
+    }
+
+    fn remove_params(&mut self, pairs: &[String]) -> WSResult<Vec<String>> {
+        let mut req_params = vec![];
+        for req_param in self.subscribe_params(pairs)? {
+            let mut req_param: OkexReqParam = serde_json::from_str(&req_param)?;
+            req_param.op = "unsubscribe".to_string();
+            req_params.push(serde_json::to_string(&req_param)?);
+        }
+        Ok(req_params)
+    }
+
+    fn has_subscribe_ack(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        self.books.clear();
+    }
+
+    fn streams(&self) -> &[StreamKind] {
+        &[StreamKind::Ticker, StreamKind::Trades, StreamKind::Book]
+    }
+
+    fn markets(&self) -> &[MarketType] {
+        &[MarketType::Spot, MarketType::Swap, MarketType::Futures]
+    }
+
+    fn subscribe_params(&self, pairs: &[String]) -> WSResult<Vec<String>> {
+        let mut req_param: OkexReqParam = serde_json::from_value(self.config.req_param.clone())?;
+
+        for symbol in helpers::exchange_symbols(self, pairs) {
+            let channels =
+                std::iter::once(self.channel()).chain(self.derivative_channels().iter().copied());
+            for channel in channels {
+                req_param.args.push(OkexReqParamArg {
+                    channel: channel.to_string(),
+                    inst_id: symbol.clone(),
+                });
+            }
+        }
+        Ok(vec![serde_json::to_string(&req_param)?])
+    }
+
+    // swaps end with `-SWAP` and dated futures with the expiry, like `BTC-USDT-231229`
+    fn symbol(&self, base: &str, quote: &str) -> String {
+        let symbol = format!("{}-{}", base.to_uppercase(), quote.to_uppercase());
+        match (self.config.market, &self.config.expiry) {
+            (MarketType::Swap, _) => format!("{}-SWAP", symbol),
+            (MarketType::Futures, Some(expiry)) => format!("{}-{}", symbol, expiry),
+            _ => symbol,
+        }
+    }
+
+    fn normalise_symbol(&self, symbol: &str) -> String {
+        helpers::pair_key(symbol)
+    }
+
+    /// parse okex data from socket response
+    fn parse_response(&mut self, msg: Value) -> WSResult<ResponseEnum> {
+        // error does not carry the arg, rejected instId is in the message like "instId:ABC-USDT doesn't exist"
+        if msg["event"] == "error" {
+            let reason = msg["msg"].as_str().unwrap_or_default().to_string();
+            let symbols = reason
+                .split("instId:")
+                .nth(1)
+                .and_then(|rest| {
+                    rest.split(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+                        .next()
+                })
+                .filter(|symbol| !symbol.is_empty())
+                .map(str::to_string);
+            return Ok(ResponseEnum::Ack(SubscribeAck::Rejected(
+                symbols.into_iter().collect(),
+                reason,
+            )));
+        }
+        if msg["event"] == "subscribe" {
+            let symbols = msg["arg"]["instId"].as_str().map(str::to_string);
+            return Ok(ResponseEnum::Ack(SubscribeAck::Accepted(
+                symbols.into_iter().collect(),
+            )));
+        }
+
+        if msg["arg"]["channel"] == "mark-price" {
+            let marks: OkexData<OkexMarkPrice> = match serde_json::from_value(msg) {
+                Ok(marks) => marks,
+                Err(_) => return Ok(ResponseEnum::Ignore),
+            };
+            let updates = marks
+                .data
+                .into_iter()
+                .map(|mark| DerivativeUpdate {
+                    key: self.normalise_symbol(&mark.inst_id),
+                    mark_price: Some(mark.mark_px),
+                    event_time: mark.ts.and_then(|ts| ts.parse().ok()),
+                    ..Default::default()
+                })
+                .collect();
+            return Ok(ResponseEnum::Derivatives(updates));
+        }
+        if msg["arg"]["channel"] == "funding-rate" {
+            let rates: OkexData<OkexFundingRate> = match serde_json::from_value(msg) {
+                Ok(rates) => rates,
+                Err(_) => return Ok(ResponseEnum::Ignore),
+            };
+            let updates = rates
+                .data
+                .into_iter()
+                .map(|rate| DerivativeUpdate {
+                    key: self.normalise_symbol(&rate.inst_id),
+                    funding_rate: Some(rate.funding_rate),
+                    funding_time: rate.funding_time.and_then(|time| time.parse().ok()),
+                    event_time: rate.ts.and_then(|ts| ts.parse().ok()),
+                    ..Default::default()
+                })
+                .collect();
+            return Ok(ResponseEnum::Derivatives(updates));
+        }
+
+        if msg["arg"]["channel"] == "books" {
+            let books: OkexBooks = match serde_json::from_value(msg) {
+                Ok(books) => books,
+                Err(_) => return Ok(ResponseEnum::Ignore),
+            };
+            return self.books_update(books);
+        }
+
+        if msg["arg"]["channel"] == "trades" {
+            let trades: OkexTrades = match serde_json::from_value(msg) {
+                Ok(trades) => trades,
+                Err(_) => return Ok(ResponseEnum::Ignore),
+            };
+            let prices = trades
+                .data
+                .into_iter()
+                .map(|trade| PriceUpdate {
+                    key: self.normalise_symbol(&trade.inst_id),
+                    ticker: Ticker {
+                        event_time: trade.ts.parse().ok(),
+                        ..Ticker::new(trade.px.clone())
+                    },
+                    received: None,
+                    conversion: None,
+                    trade: Some(Trade {
+                        id: trade.trade_id,
+                        price: trade.px,
+                        size: trade.sz,
+                        side: trade.side,
+                    }),
+                    market: None,
+                })
+                .collect();
+            return Ok(ResponseEnum::Prices(prices));
+        }
+
+        // Serialize okex response, anything else is ignored
+        let okex_response: OkexResponse = match serde_json::from_value(msg) {
+            Ok(p) => p,
+            Err(_) => return Ok(ResponseEnum::Ignore),
+        };
+        let prices = okex_response
+            .data
+            .into_iter()
+            .map(|data| PriceUpdate {
+                key: self.normalise_symbol(&data.inst_id),
+                ticker: Ticker {
+                    last: data.last,
+                    bid: data.bid_px,
+                    ask: data.ask_px,
+                    bid_size: data.bid_sz,
+                    ask_size: data.ask_sz,
+                    volume_24h: data.vol_24h,
+                    event_time: data.ts.and_then(|ts| ts.parse().ok()),
+                },
+                received: None,
+                conversion: None,
+                trade: None,
+                market: None,
+            })
+            .collect();
+        Ok(ResponseEnum::Prices(prices))
+    }
+}
@@ -0,0 +1,847 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, SystemTime},
+};
+
+use futures_util::{future, SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio::task::{JoinError, JoinHandle};
+use tokio::time::{self, Instant, Interval};
+use tokio_tungstenite::{client_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use url::Url;
+
+use crate::{
+    bridge::{self, QuoteBridge},
+    errors::WSError,
+    exchanges::Exchange,
+    helpers, parser, proxy, rest, tls,
+    types::{
+        ConnectionState, Keepalive, MarketType, ReceiveTime, ReconnectPolicy, ResponseEnum,
+        StateEvent, SubscribeAck, SubscribeReport, WSResult, WebSocketConfig,
+    },
+};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// rest snapshot load of an exchange symbol
+type SnapshotLoad = (String, JoinHandle<WSResult<Value>>);
+
+/// longest wait for the close handshake on shutdown
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// state events kept for slow subscribers
+const EVENTS_CAPACITY: usize = 64;
+
+#[derive(Debug)]
+/// Web socket handler of one exchange, reconnect by itself when the socket drops
+pub struct WSHandler {
+    exchange: Box<dyn Exchange>,
+    /// exchange name with the connection number when pairs are split over connections
+    label: String,
+    pairs: Vec<String>,
+    /// pairs cache key of each normalised exchange symbol of the pairs
+    keys: HashMap<String, String>,
+    bridge: QuoteBridge,
+    socket_stream: Option<Socket>,
+    keepalive: Keepalive,
+    heartbeat: Option<Interval>,
+    /// last time a price came from the socket, or it was connected
+    last_price: Instant,
+    /// no price within the stale timeout since last connect
+    stale: bool,
+    /// successful connect and subscribe, first one included
+    connections: u32,
+    /// failed reconnect attempts in a row
+    attempt: u32,
+    retry_at: Option<Instant>,
+    /// connect and subscribe of a new socket, it goes on when the read future is dropped
+    reopen: Option<JoinHandle<WSResult<Socket>>>,
+    /// reconnect gave up, socket is not used any more
+    stopped: bool,
+    /// acks of the first subscribe, none when the exchange does not send acks
+    report: Option<SubscribeReport>,
+    /// responses read while waiting for subscribe acks
+    buffered: VecDeque<ResponseEnum>,
+    /// rest snapshots being loaded, they go on when the read future is dropped
+    snapshots: Vec<SnapshotLoad>,
+    state: ConnectionState,
+    /// time of the last state change
+    state_since: SystemTime,
+    events: broadcast::Sender<StateEvent>,
+}
+
+impl WSHandler {
+    pub fn new(exchange: Box<dyn Exchange>, pairs: Vec<String>) -> WSHandler {
+        WSHandler::new_shard(exchange, pairs, 0, 1)
+    }
+
+    /// handler of one connection out of `shards` connections of the exchange
+    pub fn new_shard(
+        exchange: Box<dyn Exchange>,
+        pairs: Vec<String>,
+        shard: usize,
+        shards: usize,
+    ) -> WSHandler {
+        let keepalive = exchange.keepalive();
+        let label = if shards > 1 {
+            format!("{}#{}", exchange.name(), shard + 1)
+        } else {
+            exchange.name().to_string()
+        };
+        let bridges = &exchange.config().bridges;
+        let keys = helpers::symbol_keys(exchange.as_ref(), &bridge::venue_pairs(bridges, &pairs));
+        let bridge = QuoteBridge::new(bridges, &pairs);
+        WSHandler {
+            exchange,
+            label,
+            pairs,
+            keys,
+            bridge,
+            socket_stream: None,
+            keepalive,
+            heartbeat: None,
+            last_price: Instant::now(),
+            stale: false,
+            connections: 0,
+            attempt: 0,
+            retry_at: None,
+            reopen: None,
+            stopped: false,
+            report: None,
+            buffered: VecDeque::new(),
+            snapshots: vec![],
+            state: ConnectionState::Disconnected,
+            state_since: SystemTime::now(),
+            events: broadcast::channel(EVENTS_CAPACITY).0,
+        }
+    }
+
+    /// exchange name of the handler
+    pub fn name(&self) -> &str {
+        self.exchange.name()
+    }
+
+    /// exchange name with the connection number, used in logs
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// how many times the socket has been reconnected
+    pub fn reconnect_count(&self) -> u32 {
+        self.connections.saturating_sub(1)
+    }
+
+    /// accepted and rejected pairs of the subscribe
+    pub fn subscribe_report(&self) -> Option<&SubscribeReport> {
+        self.report.as_ref()
+    }
+
+    /// feed has not sent a price within the stale timeout
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    /// current connection state
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    /// time of the last state change
+    pub fn state_since(&self) -> SystemTime {
+        self.state_since
+    }
+
+    /// subscribe to state changes of the handler
+    pub fn events(&self) -> broadcast::Receiver<StateEvent> {
+        self.events.subscribe()
+    }
+
+    /// send state changes to a channel shared with other handlers
+    pub fn set_events(&mut self, events: broadcast::Sender<StateEvent>) {
+        self.events = events;
+    }
+
+    /// move to the state and publish the change
+    fn set_state(&mut self, state: ConnectionState) {
+        if self.state == state {
+            return;
+        }
+        let event = StateEvent {
+            exchange: self.label.clone(),
+            from: self.state,
+            to: state,
+            at: SystemTime::now(),
+        };
+        self.state = state;
+        self.state_since = event.at;
+        // nobody listening is fine
+        let _ = self.events.send(event);
+    }
+
+    fn policy(&self) -> &ReconnectPolicy {
+        &self.exchange.config().reconnect
+    }
+
+    /// connect to web socket
+    pub async fn connect(&mut self) -> WSResult<()> {
+        self.set_state(ConnectionState::Connecting);
+        let socket = self.connect_socket().await?;
+        self.set_socket(socket);
+        Ok(())
+    }
+
+    /// connect socket of the exchange url, through the proxy when there is one
+    async fn connect_socket(&self) -> WSResult<Socket> {
+        let url = self.exchange.ws_url(&self.venue_pairs())?;
+        open_socket(self.exchange.config(), url).await
+    }
+
+    /// subscribe web socket
+    pub async fn subscribe(&mut self) -> WSResult<()> {
+        let venue_pairs = self.venue_pairs();
+        let req_params: Vec<String> = self.exchange.subscribe_params(&venue_pairs)?;
+        let socket = self
+            .socket_stream
+            .as_mut()
+            .ok_or(WSError::SocketNotConnected)?;
+
+        for req_param in req_params {
+            socket.send(Message::Text(req_param)).await?;
+        }
+        self.connections += 1;
+        self.set_state(ConnectionState::Subscribing);
+
+        if self.exchange.has_subscribe_ack() {
+            self.report = Some(SubscribeReport {
+                pending: venue_pairs,
+                ..Default::default()
+            });
+            self.wait_acks().await?;
+        }
+        self.set_state(ConnectionState::Subscribed);
+        Ok(())
+    }
+
+    /// read acks until every pair is answered or subscribe timeout, prices are kept for read
+    async fn wait_acks(&mut self) -> WSResult<()> {
+        let timeout = Duration::from_millis(self.exchange.config().subscribe_timeout_ms);
+        let deadline = Instant::now() + timeout;
+        // pending pairs are sent one request each after a rejection without symbol
+        let mut split = false;
+
+        while self
+            .report
+            .as_ref()
+            .is_some_and(|report| !report.pending.is_empty())
+        {
+            let socket = self
+                .socket_stream
+                .as_mut()
+                .ok_or(WSError::SocketNotConnected)?;
+            let msg = match time::timeout_at(deadline, socket.next()).await {
+                Err(_) => break,
+                Ok(Some(Ok(Message::Ping(_) | Message::Pong(_)))) => continue,
+                Ok(Some(Ok(Message::Close(_)))) => return Err(WSError::SocketNotConnected),
+                Ok(Some(Ok(msg))) => msg,
+                Ok(Some(Err(error))) => return Err(error.into()),
+                Ok(None) => return Err(WSError::SocketNotConnected),
+            };
+            if is_pong(&self.keepalive, &msg) {
+                continue;
+            }
+            match parser::message_parser(self.exchange.as_mut(), Ok(msg))? {
+                ResponseEnum::Ack(ack) => {
+                    let rejected = matches!(ack, SubscribeAck::Rejected(..));
+                    let named = self.apply_ack(ack);
+                    if rejected && !split && self.exchange.rejects_whole_request() {
+                        split = !named;
+                        self.resubscribe_pending(split).await?;
+                    }
+                }
+                ResponseEnum::Reply(reply) => socket.send(Message::Text(reply)).await?,
+                ResponseEnum::Resync(symbol) => self.resync(&symbol),
+                ResponseEnum::Ignore => {}
+                mut response => {
+                    stamp(
+                        &mut response,
+                        &self.keys,
+                        &mut self.bridge,
+                        ReceiveTime::now(),
+                        self.exchange.config().market,
+                    );
+                    if !is_empty(&response) {
+                        self.buffered.push_back(response);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// move pending pairs of the ack to accepted or rejected, rejected pairs are not subscribed again
+    ///
+    /// Return false when the ack names no pending pair, such a rejection is logged and the pairs stay pending.
+    fn apply_ack(&mut self, ack: SubscribeAck) -> bool {
+        let exchange = self.exchange.as_ref();
+        let report = match self.report.as_mut() {
+            Some(report) => report,
+            None => return false,
+        };
+        let symbol = |pair: &String| helpers::pair_symbol(exchange, pair).unwrap_or_default();
+        let has_symbol = |symbols: &[String], pair: &String| {
+            let symbol = symbol(pair);
+            symbols.iter().any(|s| s.eq_ignore_ascii_case(&symbol))
+        };
+
+        let (acked, pending): (Vec<String>, Vec<String>) =
+            report.pending.drain(..).partition(|pair| match &ack {
+                SubscribeAck::All => true,
+                SubscribeAck::Accepted(symbols) | SubscribeAck::Rejected(symbols, _)
+                    if !symbols.is_empty() =>
+                {
+                    has_symbol(symbols, pair)
+                }
+                SubscribeAck::Accepted(_) => false,
+                SubscribeAck::Rejected(_, reason) => {
+                    helpers::mentions_symbol(reason, &symbol(pair))
+                }
+            });
+        report.pending = pending;
+        let named = !acked.is_empty();
+
+        match ack {
+            SubscribeAck::All | SubscribeAck::Accepted(_) => report.accepted.extend(acked),
+            SubscribeAck::Rejected(_, reason) => {
+                if acked.is_empty() {
+                    println!("Exchange: {} error: {}", self.label, reason);
+                }
+                self.pairs.retain(|pair| !acked.contains(pair));
+                report
+                    .rejected
+                    .extend(acked.into_iter().map(|pair| (pair, reason.clone())));
+            }
+        }
+        named
+    }
+
+    /// send the pending pairs again after a failed subscribe request, one request per pair when split
+    async fn resubscribe_pending(&mut self, split: bool) -> WSResult<()> {
+        let pending = match self.report.as_ref() {
+            Some(report) if !report.pending.is_empty() => report.pending.clone(),
+            _ => return Ok(()),
+        };
+        let req_params = if split {
+            let mut req_params = vec![];
+            for pair in pending {
+                req_params.extend(self.exchange.subscribe_params(&[pair])?);
+            }
+            req_params
+        } else {
+            self.exchange.subscribe_params(&pending)?
+        };
+        let socket = self
+            .socket_stream
+            .as_mut()
+            .ok_or(WSError::SocketNotConnected)?;
+        for req_param in req_params {
+            socket.send(Message::Text(req_param)).await?;
+        }
+        Ok(())
+    }
+
+    /// connect and subscribe within the connect timeout of the exchange
+    pub async fn start(&mut self) -> WSResult<()> {
+        let name = self.label().to_string();
+        let timeout = Duration::from_millis(self.exchange.config().connect_timeout_ms);
+        let started = time::timeout(timeout, async {
+            self.connect().await?;
+            self.subscribe().await
+        })
+        .await
+        .map_err(|_| WSError::ConnectTimeout(name))
+        .and_then(|started| started);
+        if started.is_err() {
+            self.set_state(ConnectionState::Failed);
+        }
+        started
+    }
+
+    /// send close frame and stop reading the socket
+    pub async fn close(&mut self) {
+        self.stopped = true;
+        self.heartbeat = None;
+        self.abort_snapshots();
+        self.abort_reopen();
+        if let Some(mut socket) = self.socket_stream.take() {
+            // exchange may not answer the close frame
+            let _ = time::timeout(CLOSE_TIMEOUT, socket.close(None)).await;
+        }
+        self.set_state(ConnectionState::Closed);
+    }
+
+    /// subscribe pairs on the connected socket, they are subscribed again on reconnect
+    pub async fn add_pairs(&mut self, pairs: &[String]) -> WSResult<()> {
+        let pairs: Vec<String> = pairs
+            .iter()
+            .filter(|pair| !self.pairs.contains(pair))
+            .cloned()
+            .collect();
+        if pairs.is_empty() {
+            return Ok(());
+        }
+
+        let before = self.venue_pairs();
+        let mut all = self.pairs.clone();
+        all.extend(pairs);
+        let added: Vec<String> = bridge::venue_pairs(&self.exchange.config().bridges, &all)
+            .into_iter()
+            .filter(|pair| !before.contains(pair))
+            .collect();
+
+        let req_params = if added.is_empty() {
+            vec![]
+        } else {
+            self.exchange.add_params(&added)?
+        };
+        if let Some(report) = self.report.as_mut() {
+            report.pending.extend(added);
+        }
+        self.pairs = all;
+        self.refresh_pairs();
+        self.send_all(req_params).await;
+        Ok(())
+    }
+
+    /// unsubscribe pairs on the connected socket
+    pub async fn remove_pairs(&mut self, pairs: &[String]) -> WSResult<()> {
+        let pairs: Vec<String> = pairs
+            .iter()
+            .filter(|pair| self.pairs.contains(pair))
+            .cloned()
+            .collect();
+        if pairs.is_empty() {
+            return Ok(());
+        }
+
+        let mut rest = self.pairs.clone();
+        rest.retain(|pair| !pairs.contains(pair));
+        // conversion pair stays while another bridged pair needs it
+        let after = bridge::venue_pairs(&self.exchange.config().bridges, &rest);
+        let removed: Vec<String> = self
+            .venue_pairs()
+            .into_iter()
+            .filter(|pair| !after.contains(pair))
+            .collect();
+
+        let req_params = if removed.is_empty() {
+            vec![]
+        } else {
+            self.exchange.remove_params(&removed)?
+        };
+        if let Some(report) = self.report.as_mut() {
+            report.accepted.retain(|pair| !removed.contains(pair));
+            report.pending.retain(|pair| !removed.contains(pair));
+        }
+        self.pairs = rest;
+        self.refresh_pairs();
+        self.send_all(req_params).await;
+        Ok(())
+    }
+
+    /// pairs subscribed on the socket
+    pub fn pairs(&self) -> &[String] {
+        &self.pairs
+    }
+
+    /// pairs subscribed on the venue, bridged pairs in the venue quote with their conversion pair
+    pub fn venue_pairs(&self) -> Vec<String> {
+        bridge::venue_pairs(&self.exchange.config().bridges, &self.pairs)
+    }
+
+    /// lookups of symbol keys and bridge after pairs changed
+    fn refresh_pairs(&mut self) {
+        self.keys = helpers::symbol_keys(self.exchange.as_ref(), &self.venue_pairs());
+        self.bridge
+            .set_pairs(&self.exchange.config().bridges, &self.pairs);
+    }
+
+    /// send requests on the connected socket, a dropped socket is subscribed with current pairs on reconnect
+    async fn send_all(&mut self, req_params: Vec<String>) {
+        let socket = match self.socket_stream.as_mut() {
+            Some(socket) => socket,
+            // reconnect in flight started with the old pairs
+            None => return self.abort_reopen(),
+        };
+        for req_param in req_params {
+            if socket.send(Message::Text(req_param)).await.is_err() {
+                self.disconnected();
+                return;
+            }
+        }
+    }
+
+    /// use the socket and restart keepalive and stale timer for it
+    fn set_socket(&mut self, socket: Socket) {
+        self.socket_stream = Some(socket);
+        self.heartbeat = self
+            .keepalive
+            .interval()
+            .map(|interval| time::interval_at(Instant::now() + interval, interval));
+        self.last_price = Instant::now();
+    }
+
+    /// instant when the feed becomes stale, none when it is already stale or not checked
+    fn stale_at(&self) -> Option<Instant> {
+        match &self.exchange.config().stale {
+            Some(stale) if !self.stale => {
+                Some(self.last_price + Duration::from_millis(stale.timeout_ms))
+            }
+            _ => None,
+        }
+    }
+
+    /// message sent on each keepalive tick
+    fn keepalive_message(&self) -> Option<Message> {
+        match &self.keepalive {
+            Keepalive::Ping { .. } => Some(Message::Ping(vec![])),
+            Keepalive::Text { message, .. } => Some(Message::Text(message.clone())),
+            Keepalive::None | Keepalive::Channel => None,
+        }
+    }
+
+    /// read next socket message and parse it, reconnect when the socket drops
+    ///
+    /// Reconnect and snapshot loads run in their own tasks and backoff state is kept in the handler,
+    /// so the future can be dropped and read again.
+    pub async fn read(&mut self) -> Option<WSResult<ResponseEnum>> {
+        loop {
+            if let Some(response) = self.buffered.pop_front() {
+                return Some(Ok(response));
+            }
+            if self.stopped {
+                return future::pending().await;
+            }
+            if self.socket_stream.is_none() {
+                let reopen = self.reopen.get_or_insert_with(|| {
+                    self.exchange.reset();
+                    spawn_reopen(self.exchange.as_ref(), &self.pairs, self.retry_at)
+                });
+                let opened = reopen
+                    .await
+                    .unwrap_or_else(|error| Err(std::io::Error::from(error).into()));
+                self.reopen = None;
+                if let Err(error) = self.reopened(opened) {
+                    self.stopped = true;
+                    self.set_state(ConnectionState::Failed);
+                    return Some(Err(error));
+                }
+                continue;
+            }
+            let stale_at = self.stale_at();
+            let keepalive = self.keepalive_message();
+            let socket = match self.socket_stream.as_mut() {
+                Some(socket) => socket,
+                None => continue,
+            };
+
+            tokio::select! {
+                msg = socket.next() => {
+                    let msg = match msg {
+                        // stream ends right after close frame
+                        Some(Ok(Message::Close(_))) => continue,
+                        // pings are answered by the socket itself
+                        Some(Ok(Message::Ping(_) | Message::Pong(_))) => continue,
+                        Some(Ok(msg)) => msg,
+                        Some(Err(_)) | None => {
+                            self.disconnected();
+                            continue;
+                        }
+                    };
+                    if is_pong(&self.keepalive, &msg) {
+                        continue;
+                    }
+                    match parser::message_parser(self.exchange.as_mut(), Ok(msg)) {
+                        Ok(ResponseEnum::Reply(reply)) => {
+                            if socket.send(Message::Text(reply)).await.is_err() {
+                                self.disconnected();
+                            }
+                        }
+                        // late acks and acks of a resubscribe
+                        Ok(ResponseEnum::Ack(ack)) => {
+                            self.apply_ack(ack);
+                        }
+                        Ok(ResponseEnum::Resync(symbol)) => self.resync(&symbol),
+                        response => {
+                            let mut response = response;
+                            if let Ok(
+                                response @ (ResponseEnum::Prices(_) | ResponseEnum::Derivatives(_)),
+                            ) = &mut response
+                            {
+                                let received = ReceiveTime::now();
+                                let market = self.exchange.config().market;
+                                stamp(response, &self.keys, &mut self.bridge, received, market);
+                                self.last_price = received.instant.into();
+                                self.stale = false;
+                                self.set_state(ConnectionState::Streaming);
+                                // only rates of bridged pairs
+                                if is_empty(response) {
+                                    continue;
+                                }
+                            }
+                            return Some(response);
+                        }
+                    }
+                },
+                _ = tick(&mut self.heartbeat) => {
+                    if let Some(message) = keepalive {
+                        if socket.send(message).await.is_err() {
+                            self.disconnected();
+                        }
+                    }
+                },
+                (symbol, loaded) = next_snapshot(&mut self.snapshots) => {
+                    let loaded = loaded.unwrap_or_else(|error| {
+                        Err(WSError::SnapshotFailed(self.label().to_string(), error.to_string()))
+                    });
+                    self.snapshot_loaded(&symbol, loaded);
+                },
+                _ = sleep_until(stale_at) => {
+                    self.stale = true;
+                    self.set_state(ConnectionState::Stale);
+                    if self.exchange.config().stale.as_ref().is_some_and(|stale| stale.reconnect) {
+                        self.disconnected();
+                    }
+                }
+            }
+        }
+    }
+
+    /// start loading the order book of the exchange symbol again, once at a time for a symbol
+    ///
+    /// Snapshot is loaded in its own task and applied by a later read.
+    /// Exchange without rest snapshot sends the book again when the socket is reconnected.
+    fn resync(&mut self, symbol: &str) {
+        if self.snapshots.iter().any(|(loading, _)| loading == symbol) {
+            return;
+        }
+        let url = match self.exchange.snapshot_url(symbol) {
+            Ok(Some(url)) => url,
+            Ok(None) => return self.disconnected(),
+            Err(error) => {
+                println!("Exchange: {} {}", self.label(), error);
+                return self.disconnected();
+            }
+        };
+        let config = self.exchange.config().clone();
+        let load = tokio::spawn(async move {
+            let timeout = Duration::from_millis(config.connect_timeout_ms);
+            time::timeout(timeout, rest::get_json(&config, &url))
+                .await
+                .map_err(|_| {
+                    WSError::SnapshotFailed(config.name.clone(), "timed out".to_string())
+                })?
+        });
+        self.snapshots.push((symbol.to_string(), load));
+    }
+
+    /// apply the loaded snapshot, prices of the book are read next
+    fn snapshot_loaded(&mut self, symbol: &str, loaded: WSResult<Value>) {
+        match loaded.and_then(|snapshot| self.exchange.apply_snapshot(symbol, snapshot)) {
+            Ok(mut response @ ResponseEnum::Prices(_)) => {
+                stamp(
+                    &mut response,
+                    &self.keys,
+                    &mut self.bridge,
+                    ReceiveTime::now(),
+                    self.exchange.config().market,
+                );
+                if !is_empty(&response) {
+                    self.buffered.push_back(response);
+                }
+            }
+            // snapshot is older than the diffs kept for it
+            Ok(ResponseEnum::Resync(symbol)) => self.resync(&symbol),
+            Ok(_) => {}
+            Err(error) => {
+                println!("Exchange: {} {}", self.label(), error);
+                self.disconnected();
+            }
+        }
+    }
+
+    /// stop the snapshot loads, books are reset by the next connect
+    fn abort_snapshots(&mut self) {
+        for (_, load) in self.snapshots.drain(..) {
+            load.abort();
+        }
+    }
+
+    /// stop the reconnect in flight, the next read starts it again
+    fn abort_reopen(&mut self) {
+        if let Some(reopen) = self.reopen.take() {
+            reopen.abort();
+        }
+    }
+
+    /// drop the socket and schedule the first reconnect attempt
+    fn disconnected(&mut self) {
+        self.set_state(ConnectionState::Reconnecting);
+        self.socket_stream = None;
+        self.heartbeat = None;
+        self.abort_snapshots();
+        self.abort_reopen();
+        self.attempt = 0;
+        self.retry_at = Some(Instant::now() + self.policy().delay(0));
+    }
+
+    /// use the reconnected socket, or schedule the next attempt until max retries is reached
+    fn reopened(&mut self, opened: WSResult<Socket>) -> WSResult<()> {
+        match opened {
+            Ok(socket) => {
+                self.set_socket(socket);
+                self.connections += 1;
+                self.set_state(ConnectionState::Subscribed);
+                self.attempt = 0;
+                self.retry_at = None;
+            }
+            Err(error) => {
+                self.attempt += 1;
+                println!(
+                    "Exchange: {} reconnect attempt {} failed: {}",
+                    self.label(),
+                    self.attempt,
+                    error
+                );
+                if let Some(max_retries) = self.policy().max_retries {
+                    if self.attempt >= max_retries {
+                        return Err(WSError::ReconnectFailed(
+                            self.label().to_string(),
+                            self.attempt,
+                        ));
+                    }
+                }
+                self.retry_at = Some(Instant::now() + self.policy().delay(self.attempt));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// connect socket of the url, through the proxy when there is one
+async fn open_socket(config: &WebSocketConfig, url: Url) -> WSResult<Socket> {
+    let proxy = proxy::proxy_url(config, &url)?;
+
+    let stream = proxy::open_stream(&config.name, &url, proxy.as_ref()).await?;
+    let stream = tls::wrap_stream(&config.name, &url, &config.tls, stream).await?;
+    let (socket, _response) = client_async(url, stream).await?;
+    Ok(socket)
+}
+
+/// connect and subscribe a new socket in its own task after the retry instant
+fn spawn_reopen(
+    exchange: &dyn Exchange,
+    pairs: &[String],
+    retry_at: Option<Instant>,
+) -> JoinHandle<WSResult<Socket>> {
+    let config = exchange.config().clone();
+    let venue_pairs = bridge::venue_pairs(&config.bridges, pairs);
+    let request = exchange
+        .ws_url(&venue_pairs)
+        .and_then(|url| Ok((url, exchange.subscribe_params(&venue_pairs)?)));
+    tokio::spawn(async move {
+        if let Some(retry_at) = retry_at {
+            time::sleep_until(retry_at).await;
+        }
+        let (url, req_params) = request?;
+        let mut socket = open_socket(&config, url).await?;
+        for req_param in req_params {
+            socket.send(Message::Text(req_param)).await?;
+        }
+        Ok(socket)
+    })
+}
+
+/// set pairs cache key, receive time and market of prices, keys of unknown symbols are kept, then bridge them
+///
+/// Mark prices and funding rates get key and receive time, they are not bridged.
+fn stamp(
+    response: &mut ResponseEnum,
+    keys: &HashMap<String, String>,
+    bridge: &mut QuoteBridge,
+    received: ReceiveTime,
+    market: MarketType,
+) {
+    match response {
+        ResponseEnum::Prices(prices) => {
+            for price in prices.iter_mut() {
+                if let Some(key) = keys.get(&price.key) {
+                    price.key.clone_from(key);
+                }
+                price.received.get_or_insert(received);
+                if market != MarketType::Spot {
+                    price.market = Some(market);
+                }
+            }
+            *prices = bridge.convert(std::mem::take(prices));
+        }
+        ResponseEnum::Derivatives(updates) => {
+            for update in updates.iter_mut() {
+                if let Some(key) = keys.get(&update.key) {
+                    update.key.clone_from(key);
+                }
+                update.received.get_or_insert(received);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// prices response without any price
+fn is_empty(response: &ResponseEnum) -> bool {
+    matches!(response, ResponseEnum::Prices(prices) if prices.is_empty())
+}
+
+/// plain text answer of the keepalive which is not json
+fn is_pong(keepalive: &Keepalive, msg: &Message) -> bool {
+    match (keepalive, msg) {
+        (
+            Keepalive::Text {
+                pong: Some(pong), ..
+            },
+            Message::Text(text),
+        ) => text == pong,
+        _ => false,
+    }
+}
+
+/// symbol and result of the first finished snapshot load, pending when none is loading
+async fn next_snapshot(
+    snapshots: &mut Vec<SnapshotLoad>,
+) -> (String, Result<WSResult<Value>, JoinError>) {
+    if snapshots.is_empty() {
+        return future::pending().await;
+    }
+    let (loaded, index, _) = future::select_all(snapshots.iter_mut().map(|(_, load)| load)).await;
+    let (symbol, _) = snapshots.remove(index);
+    (symbol, loaded)
+}
+
+/// sleep until the instant, never complete without one
+async fn sleep_until(instant: Option<Instant>) {
+    match instant {
+        Some(instant) => time::sleep_until(instant).await,
+        None => future::pending().await,
+    }
+}
+
+/// wait for next tick of the interval, never complete without interval
+async fn tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => future::pending().await,
+    }
+}
@@ -1,148 +1,234 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
+use serde_json::Value;
+
+use crate::exchanges::Exchange;
 use crate::types::{
-    BinanceReqParam, CoinbaseReqParam, OkexReqParam, OkexReqParamArg, PairsCache, PricesPairs,
-    ResponseEnum, SocketType, WSResult, WebSocketConfig,
+    Decimal, DerivativePoint, DerivativesCache, LatencyStats, MarketType, Pair, PairsCache,
+    PriceUpdate, PricesPairs, ResponseEnum, WSResult,
 };
-use serde_json::Value;
 
-/// binance web socket request url handle for pairs and return
-pub fn binance_req_url(ws_base_url: &str, pairs: &Vec<String>) -> String {
-    let mut binance_ws_api: String = format!("{}/ws", ws_base_url);
-
-    for pair in pairs {
-        let coin: Vec<&str> = pair.split("_").collect();
-        if coin.len() == 2 {
-            let query: String = format!(
-                "/{}{}@ticker",
-                coin[0].to_lowercase(),
-                coin[1].to_lowercase()
-            );
-            binance_ws_api.push_str(&query)
-        }
+/// exchange symbol of one pair, none when the pair is not valid
+pub fn pair_symbol<E: Exchange + ?Sized>(exchange: &E, pair: &str) -> Option<String> {
+    Pair::parse(pair).map(|pair| exchange.pair_symbol(&pair))
+}
+
+/// symbol is a whole word of the text, `ETH-USD` is not found in `ETH-USDT`
+pub fn mentions_symbol(text: &str, symbol: &str) -> bool {
+    if symbol.is_empty() {
+        return false;
     }
+    let text = text.to_uppercase();
+    let symbol = symbol.to_uppercase();
+    let is_word = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '/');
+    text.match_indices(&symbol).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + symbol.len()..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })
+}
 
-    binance_ws_api
+/// exchange symbols of valid pairs
+pub fn exchange_symbols<E: Exchange + ?Sized>(exchange: &E, pairs: &[String]) -> Vec<String> {
+    pairs
+        .iter()
+        .filter_map(|pair| pair_symbol(exchange, pair))
+        .collect()
 }
 
-/// create request parameters for Binance, Coinbase and Okex
-pub fn create_req_params(
-    socket_type: SocketType,
-    data: &Value,
-    pairs: &Vec<String>,
-) -> WSResult<String> {
-    let mut params = vec![];
-    for pair in pairs {
-        let coin: Vec<&str> = pair.split("_").collect();
-        if coin.len() == 2 {
-            let param = match socket_type {
-                SocketType::Binance => {
-                    format!("{}{}@ticker", coin[0].to_uppercase(), coin[1].to_uppercase())
-                }
-                SocketType::Okex => {
-                    format!("{}-{}", coin[0].to_uppercase(), coin[1].to_uppercase())
-                }
-                SocketType::Coinbase => {
-                    format!("{}-{}", coin[0].to_uppercase(), coin[1].to_uppercase())
-                }
-            };
-            params.push(param);
-        }
-    }
-    get_request_param_string(socket_type, data, params)
+/// pairs cache key of every exchange symbol of the pairs, replies are looked up in it
+///
+/// Symbols are not split back into assets, `BTCUSDT` could split in more than one place.
+pub fn symbol_keys<E: Exchange + ?Sized>(
+    exchange: &E,
+    pairs: &[String],
+) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .filter_map(|pair| Pair::parse(pair))
+        .map(|pair| {
+            let symbol = exchange.normalise_symbol(&exchange.pair_symbol(&pair));
+            (symbol, pair.key())
+        })
+        .collect()
 }
 
-/// get request parameter in string
-pub fn get_request_param_string(
-    socket_type: SocketType,
-    data: &Value,
-    params: Vec<String>,
-) -> WSResult<String> {
-    match socket_type {
-        SocketType::Binance => {
-            let mut req_param: BinanceReqParam = serde_json::from_value(data.clone())?;
-
-            params.iter().for_each(|param| {
-                req_param.params.push(param.clone());
-            });
-            Ok(serde_json::to_string(&req_param)?)
-        }
-        SocketType::Okex => {
-            let mut req_param: OkexReqParam =
-                serde_json::from_value(data.clone()).expect("Can't parse okex_req_param");
-            params.iter().for_each(|param| {
-                req_param.args.push(OkexReqParamArg {
-                    channel: "tickers".to_string(),
-                    inst_id: param.clone(),
-                });
-            });
-            Ok(serde_json::to_string(&req_param)?)
-        }
-        SocketType::Coinbase => {
-            let mut req_param: CoinbaseReqParam =
-                serde_json::from_value(data.clone()).expect("Can't parse coinbase_req_param");
-            params.iter().for_each(|param| {
-                req_param.product_ids.push(param.clone());
-            });
-            Ok(serde_json::to_string(&req_param)?)
-        }
+/// milliseconds since epoch of a rfc 3339 time, none when it is not valid
+pub fn rfc3339_millis(time: &str) -> Option<u64> {
+    let time = chrono::DateTime::parse_from_rfc3339(time).ok()?;
+    u64::try_from(time.timestamp_millis()).ok()
+}
+
+/// price text of a json string or number, numbers keep the digits sent by the exchange
+///
+/// Read it from the parsed value, a struct field goes through f64 and `0.00000005` becomes `5e-8`.
+pub fn price_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(price) => Some(price.clone()),
+        Value::Number(price) => Some(price.to_string()),
+        _ => None,
     }
 }
 
 /// remove "-" from the string and return the pairkey
 pub fn pair_key(string: &str) -> String {
-    let c_pair: Vec<&str> = string.split("-").collect();
-    format!("{}{}", c_pair[0].to_uppercase(), c_pair[1].to_uppercase())
+    string.replace('-', "").to_uppercase()
 }
 
 /// common handler fror socket response
 pub fn handle_response(
     pairs_cache: &mut HashMap<String, PairsCache>,
-    ws_details: &Vec<WebSocketConfig>,
+    name: &str,
     response: ResponseEnum,
 ) -> WSResult<()> {
-    match response {
-        ResponseEnum::Binance(binance_response) => {
-            if binance_response.s != "" {
-                let price = binance_response.c.parse::<f64>()?;
-                update_price_cache(
-                    pairs_cache,
-                    binance_response.s.to_string(),
-                    ws_details[0].name.to_string(),
-                    price,
-                );
-            }
-        }
-        ResponseEnum::Okex(okex_response) => {
-            if okex_response.data.len() > 0 {
-                let price = okex_response.data[0].last.parse::<f64>()?;
-
-                // get pair cache and push okex response, name and price
-                let key = pair_key(&okex_response.data[0].inst_id);
-                update_price_cache(pairs_cache, key, ws_details[2].name.to_string(), price);
-            }
-        }
-        ResponseEnum::Coinbase(coinbase_response) => {
-            if coinbase_response.product_id != "" {
-                let price = coinbase_response.price.parse::<f64>()?;
-                // get pair cache and push coinbase response, name and price
-                let key = pair_key(&coinbase_response.product_id);
-                update_price_cache(pairs_cache, key, ws_details[1].name.to_string(), price);
-            }
+    if let ResponseEnum::Prices(prices) = response {
+        for update in prices {
+            update_price_cache(pairs_cache, name.to_string(), update)?;
         }
     }
 
     Ok(())
 }
 
+/// keep mark prices and funding rates of the response apart from the pairs cache
+pub fn handle_derivatives(
+    derivatives: &mut DerivativesCache,
+    name: &str,
+    response: &ResponseEnum,
+) -> WSResult<()> {
+    let updates = match response {
+        ResponseEnum::Derivatives(updates) => updates,
+        _ => return Ok(()),
+    };
+    let decimal = |value: &Option<String>| -> WSResult<Option<Decimal>> {
+        Ok(match value {
+            Some(value) => Some(Decimal::from_str_exact(value)?),
+            None => None,
+        })
+    };
+    for update in updates {
+        let point = DerivativePoint {
+            name: name.to_string(),
+            mark_price: decimal(&update.mark_price)?,
+            index_price: decimal(&update.index_price)?,
+            funding_rate: decimal(&update.funding_rate)?,
+            funding_time: update.funding_time,
+            received_at: update.received.map(|received| received.wall_ms()),
+            event_time: update.event_time,
+        };
+        derivatives
+            .entry(update.key.clone())
+            .or_default()
+            .push(point);
+    }
+    Ok(())
+}
+
+/// last mark price, index price and funding rate of each exchange, later points replace earlier values
+pub fn latest_derivatives(points: &[DerivativePoint]) -> BTreeMap<String, DerivativePoint> {
+    let mut latest: BTreeMap<String, DerivativePoint> = BTreeMap::new();
+    for point in points {
+        let last = latest
+            .entry(point.name.clone())
+            .or_insert_with(|| point.clone());
+        last.mark_price = point.mark_price.or(last.mark_price);
+        last.index_price = point.index_price.or(last.index_price);
+        last.funding_rate = point.funding_rate.or(last.funding_rate);
+        last.funding_time = point.funding_time.or(last.funding_time);
+        last.received_at = point.received_at.or(last.received_at);
+        last.event_time = point.event_time.or(last.event_time);
+    }
+    latest
+}
+
 /// update price cache in hashmap
 fn update_price_cache(
     pairs_cache: &mut HashMap<String, PairsCache>,
-    key: String,
     name: String,
-    price: f64,
-) {
-    pairs_cache.get_mut(&key).map(|pair| {
-        pair.prices.push(PricesPairs { name, price });
-    });
+    update: PriceUpdate,
+) -> WSResult<()> {
+    // exact, a price with more digits than a decimal holds is an error instead of rounded
+    let mut price = Decimal::from_str_exact(&update.ticker.last)?;
+    if let Some(conversion) = &update.conversion {
+        // a zero rate or an overflow gives no price, not a zero one
+        price = match price.checked_div(conversion.rate) {
+            Some(price) => price.normalize(),
+            None => return Ok(()),
+        };
+    }
+    if let Some(pair) = pairs_cache.get_mut(&update.key) {
+        pair.prices.push(PricesPairs {
+            name,
+            price,
+            received_at: update.received.map(|received| received.wall_ms()),
+            received_mono_us: update.received.map(|received| received.monotonic_us()),
+            event_time: update.ticker.event_time,
+            conversion: update.conversion,
+            trade: update.trade,
+            ticker: Some(update.ticker),
+            market: update.market,
+        });
+    }
+    Ok(())
+}
+
+/// mean of the spot prices without rounding, none without spot prices
+///
+/// Swap and futures prices are left out. When every price is a trade the mean is weighted by trade size.
+pub fn aggregate_price(prices: &[PricesPairs]) -> Option<Decimal> {
+    let prices: Vec<&PricesPairs> = prices
+        .iter()
+        .filter(|price| matches!(price.market, None | Some(MarketType::Spot)))
+        .collect();
+    let sizes: Option<Vec<Decimal>> = prices
+        .iter()
+        .map(|price| {
+            let trade = price.trade.as_ref()?;
+            Decimal::from_str_exact(&trade.size).ok()
+        })
+        .collect();
+    if let Some(sizes) = sizes.filter(|sizes| !sizes.is_empty()) {
+        let volume: Decimal = sizes.iter().sum();
+        let amount: Decimal = prices
+            .iter()
+            .zip(&sizes)
+            .filter_map(|(price, size)| price.price.checked_mul(*size))
+            .sum();
+        if let Some(vwap) = amount.checked_div(volume) {
+            return Some(vwap.normalize());
+        }
+    }
+
+    let sum: Decimal = prices.iter().map(|price| price.price).sum();
+    sum.checked_div(Decimal::from(prices.len()))
+        .map(|mean| mean.normalize())
+}
+
+/// latency percentiles of every exchange over the data points with receive and event time
+pub fn latency_stats(pairs_cache: &HashMap<String, PairsCache>) -> BTreeMap<String, LatencyStats> {
+    let mut latencies: BTreeMap<String, Vec<i64>> = BTreeMap::new();
+    for price in pairs_cache.values().flat_map(|pair| &pair.prices) {
+        if let Some(latency) = price.latency_ms() {
+            latencies
+                .entry(price.name.clone())
+                .or_default()
+                .push(latency);
+        }
+    }
+
+    latencies
+        .into_iter()
+        .map(|(name, mut latencies)| {
+            latencies.sort_unstable();
+            let percentile = |p: usize| latencies[(latencies.len() * p).div_ceil(100).max(1) - 1];
+            let stats = LatencyStats {
+                count: latencies.len(),
+                p50: percentile(50),
+                p90: percentile(90),
+                p99: percentile(99),
+                max: latencies[latencies.len() - 1],
+            };
+            (name, stats)
+        })
+        .collect()
 }
@@ -1,20 +1,40 @@
 #[cfg(test)]
 mod test;
 
-use futures_util::StreamExt;
+use futures_util::future::{join_all, select_all};
 use std::collections::HashMap;
-use std::fs::{self, File};
+use std::fs;
 use std::time::Duration;
+use tokio::sync::broadcast;
 use tokio::time;
 
-mod types;
+pub mod types;
+use crate::errors::WSError;
+use crate::exchanges::ExchangeRegistry;
+use crate::handler::WSHandler;
 use crate::types::*;
+pub mod book;
+pub mod bridge;
+pub mod config;
 pub mod errors;
+pub mod exchanges;
+pub mod handler;
 pub mod helpers;
 pub mod parser;
+pub mod proxy;
+pub mod rest;
+pub mod tls;
 
-/// start execution
+/// file of mark prices and funding rates, kept apart from `exchanges.json`
+const DERIVATIVES_FILE: &str = "derivatives.json";
+
+/// start execution with default exchanges
 pub async fn start() -> WSResult<()> {
+    start_with_registry(ExchangeRegistry::default()).await
+}
+
+/// start execution with exchanges of the registry
+pub async fn start_with_registry(registry: ExchangeRegistry) -> WSResult<()> {
     let args: Args = Args::parse();
     let mode: String = args.mode;
 
@@ -22,12 +42,12 @@ pub async fn start() -> WSResult<()> {
         // get pairs from the argument
         let pairs: String = args.pairs;
 
-        if pairs != "" {
+        if !pairs.is_empty() {
             if check_pairs(&pairs) {
-                let pairs: Vec<_> = pairs.split(",").collect();
+                let pairs: Vec<_> = pairs.split(',').collect();
                 let pairs_string_vec: Vec<String> = pairs.iter().map(|i| i.to_string()).collect();
 
-                handle_cache_mode(pairs_string_vec).await?;
+                handle_cache_mode(pairs_string_vec, &registry).await?;
             }
         } else {
             println!("Pairs is required");
@@ -42,112 +62,268 @@ pub async fn start() -> WSResult<()> {
 
 /// check pair is valid format
 pub fn check_pairs(pairs: &str) -> bool {
-    let pairs_split: Vec<&str> = pairs.split(",").collect();
+    let pairs_split: Vec<&str> = pairs.split(',').collect();
 
     let mut count = 0;
     for pair in &pairs_split {
-        let coin: Vec<&str> = pair.split("_").collect();
-        if coin.len() == 2 {
+        if Pair::parse(pair).is_some() {
             count += 1;
             println!("Pair: {}", pair);
         } else {
             println!("Pair: {} is not valid format", pair);
         }
     }
-    if count == pairs_split.len() {
-        true
-    } else {
-        false
-    }
+    count == pairs_split.len()
 }
 
-/// handle cache mode argument and collect data from multiple exchange
-async fn handle_cache_mode(pairs: Vec<String>) -> WSResult<()> {
+/// handle cache mode argument and collect data from registered exchanges
+async fn handle_cache_mode(pairs: Vec<String>, registry: &ExchangeRegistry) -> WSResult<()> {
     // read json file of web socket urls
-    let ws_details_file: File = fs::File::open("ws_details.json")?;
+    let ws_details: WSDetails = config::load_ws_details("ws_details.json", registry)?;
 
-    let ws_details: Vec<WebSocketConfig> = serde_json::from_reader(&ws_details_file)?;
+    let mut handlers: Vec<WSHandler> = build_handlers(registry, &ws_details, &pairs);
 
-    let mut binance_handler = WSHandler::new(&ws_details[0], SocketType::Binance, pairs.clone());
-    let mut coinbase_handler = WSHandler::new(&ws_details[1], SocketType::Coinbase, pairs.clone());
-    let mut okex_handler = WSHandler::new(&ws_details[2], SocketType::Okex, pairs.clone());
-
-    // connect binance socket and subscribe
-    binance_handler.connect().await?;
-    binance_handler.subscribe().await?;
-
-    // connect coinbase socket and subscribe
-    coinbase_handler.connect().await?;
-    coinbase_handler.subscribe().await?;
-
-    // connect okex socket and subscribe
-    okex_handler.connect().await?;
-    okex_handler.subscribe().await?;
+    if handlers.is_empty() {
+        return Err(WSError::NoExchangeConfigured);
+    }
 
-    let binance_s = binance_handler.socket_stream.as_mut()
-        .expect("There is some issue in binance socket stream");
-    let (_, mut binance_read) = binance_s.split();
+    let events = share_events(&mut handlers);
+    tokio::spawn(log_state_events(events.subscribe()));
 
-    let coinbase_s = coinbase_handler.socket_stream.as_mut()
-        .expect("There is some issue in coinbase socket stream");
-    let (_, mut coinbase_read) = coinbase_s.split();
+    // connect every exchange socket and subscribe at the same time, go on without failed ones
+    let results = join_all(handlers.iter_mut().map(|handler| handler.start())).await;
+    let mut failed_exchanges: Vec<String> = vec![];
+    let mut handlers: Vec<WSHandler> = handlers
+        .into_iter()
+        .zip(results)
+        .filter_map(|(handler, result)| match result {
+            Ok(()) => {
+                print_subscribe_report(&handler);
+                Some(handler)
+            }
+            Err(error) => {
+                println!("Exchange: {} failed to connect: {}", handler.label(), error);
+                failed_exchanges.push(handler.label().to_string());
+                None
+            }
+        })
+        .collect();
 
-    let okex_s = okex_handler.socket_stream.as_mut()
-        .expect("There is some issue in okex socket stream");
-    let (_, mut okex_read) = okex_s.split();
+    if handlers.is_empty() {
+        return Err(WSError::NoExchangeConnected);
+    }
 
     let mut pairs_cache: HashMap<String, PairsCache> = HashMap::new();
+    let mut derivatives: DerivativesCache = HashMap::new();
 
     insert_pairs(pairs, &mut pairs_cache);
+    for pair_cache in pairs_cache.values_mut() {
+        pair_cache.failed_exchanges = failed_exchanges.clone();
+    }
+
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
 
     let mut interval = time::interval(Duration::from_secs(10));
     let mut interval_flag = false;
-    loop {
+    let signal = loop {
         tokio::select! {
-            msg = binance_read.next() => {
-                if let Some(msg) = msg {
-                    let response = parser::message_parser(SocketType::Binance,msg)?;
-                    helpers::handle_response(&mut pairs_cache,&ws_details, response)?;
+            (index, msg) = read_handlers(&mut handlers) => {
+                if let Some(response) = msg {
+                    let name = handlers[index].name();
+                    let handled = response.and_then(|response| {
+                        helpers::handle_derivatives(&mut derivatives, name, &response)?;
+                        helpers::handle_response(&mut pairs_cache, name, response)
+                    });
+                    // an exchange which fails is stopped, the others go on
+                    if let Err(error) = handled {
+                        let mut handler = handlers.remove(index);
+                        println!("Exchange: {} stopped: {}", handler.label(), error);
+                        handler.close().await;
+                        for pair_cache in pairs_cache.values_mut() {
+                            pair_cache.failed_exchanges.push(handler.label().to_string());
+                        }
+                        if handlers.is_empty() {
+                            break None;
+                        }
+                    }
                 }
             },
-            msg = coinbase_read.next() => {
-                if let Some(msg) = msg {
-                    let response = parser::message_parser(SocketType::Coinbase,msg)?;
-                    helpers::handle_response(&mut pairs_cache,&ws_details, response)?;
-                }
+            signal = &mut shutdown => {
+                let signal = signal?;
+                println!("Signal {} received, closing sockets", signal);
+                break Some(signal);
             },
-            msg = okex_read.next() => {
-                if let Some(msg) = msg {
-
-                    let response = parser::message_parser(SocketType::Okex,msg)?;
-                    helpers::handle_response(&mut pairs_cache,&ws_details, response)?;
-                }
-            }
             _ = interval.tick() => {
                 if interval_flag {
-                    write_pairs_cache(pairs_cache).await?;
-                    println!("Cache complete");
-                    break;
+                    break None;
                 }
                 interval_flag =true;
             }
         }
+    };
+
+    // send close frame to every exchange, then write what was collected
+    join_all(handlers.iter_mut().map(|handler| handler.close())).await;
+    write_pairs_cache(pairs_cache).await?;
+    write_derivatives_cache(&derivatives)?;
+    for handler in &handlers {
+        if handler.reconnect_count() > 0 {
+            println!(
+                "Exchange: {} reconnected {} times",
+                handler.label(),
+                handler.reconnect_count()
+            );
+        }
+        if handler.is_stale() {
+            println!("Exchange: {} feed is stale", handler.label());
+        }
+    }
+
+    match signal {
+        Some(signal) => Err(WSError::Interrupted(signal)),
+        None => {
+            println!("Cache complete");
+            Ok(())
+        }
+    }
+}
+
+/// handlers of every exchange, pairs are split over connections by the pairs limit of the exchange
+pub fn build_handlers(
+    registry: &ExchangeRegistry,
+    ws_details: &WSDetails,
+    pairs: &[String],
+) -> Vec<WSHandler> {
+    let mut handlers = vec![];
+    for exchange in registry.build(ws_details) {
+        let limit = exchange
+            .max_pairs_per_connection()
+            .unwrap_or(pairs.len())
+            .max(1);
+        let mut shards: Vec<Vec<String>> = pairs.chunks(limit).map(<[String]>::to_vec).collect();
+        if shards.len() <= 1 {
+            handlers.push(WSHandler::new(exchange, pairs.to_vec()));
+            continue;
+        }
+
+        // every connection needs its own adapter state
+        let config = exchange.config().clone();
+        let builder = registry.builder(&config);
+        let count = shards.len();
+        handlers.push(WSHandler::new_shard(exchange, shards.remove(0), 0, count));
+        if let Some(builder) = builder {
+            for (index, shard) in shards.into_iter().enumerate() {
+                handlers.push(WSHandler::new_shard(
+                    builder(&config),
+                    shard,
+                    index + 1,
+                    count,
+                ));
+            }
+        }
+    }
+    handlers
+}
+
+/// print pairs accepted and rejected by the exchange
+fn print_subscribe_report(handler: &WSHandler) {
+    if let Some(report) = handler.subscribe_report() {
+        println!(
+            "Exchange: {} accepted pairs: {:?}",
+            handler.label(),
+            report.accepted
+        );
+        for (pair, reason) in &report.rejected {
+            println!(
+                "Exchange: {} rejected pair: {} ({})",
+                handler.label(),
+                pair,
+                reason
+            );
+        }
+        if !report.pending.is_empty() {
+            println!(
+                "Exchange: {} no subscribe ack for pairs: {:?}",
+                handler.label(),
+                report.pending
+            );
+        }
     }
-    Ok(())
+}
+
+/// wait for SIGINT or SIGTERM and return the signal number
+async fn shutdown_signal() -> WSResult<i32> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut interrupt = signal(SignalKind::interrupt())?;
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            _ = interrupt.recv() => Ok(2),
+            _ = terminate.recv() => Ok(15),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await?;
+        Ok(2)
+    }
+}
+
+/// send state changes of every handler to one channel, subscribe to it to follow the connections
+pub fn share_events(handlers: &mut [WSHandler]) -> broadcast::Sender<StateEvent> {
+    let (events, _) = broadcast::channel(64);
+    for handler in handlers.iter_mut() {
+        handler.set_events(events.clone());
+    }
+    events
+}
+
+/// print state changes until every handler is dropped
+async fn log_state_events(mut events: broadcast::Receiver<StateEvent>) {
+    loop {
+        match events.recv().await {
+            Ok(event) => println!(
+                "Exchange: {} {} -> {}",
+                event.exchange, event.from, event.to
+            ),
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// read next message from whichever handler has one first, return index of the handler
+async fn read_handlers(handlers: &mut [WSHandler]) -> (usize, Option<WSResult<ResponseEnum>>) {
+    let (msg, index, _) =
+        select_all(handlers.iter_mut().map(|handler| Box::pin(handler.read()))).await;
+    (index, msg)
 }
 
 /// insert initial key and pairs in hashmap
-fn insert_pairs(pairs: Vec<String>, pairs_cache: &mut HashMap<String, PairsCache>) {
+pub fn insert_pairs(pairs: Vec<String>, pairs_cache: &mut HashMap<String, PairsCache>) {
     for pair in pairs {
-        let coin: Vec<&str> = pair.split("_").collect();
+        let pair = match Pair::parse(&pair) {
+            Some(pair) => pair,
+            None => continue,
+        };
 
-        pairs_cache.insert(
-            format!("{}{}", coin[0].to_uppercase(), coin[1].to_uppercase()),
-            PairsCache {
-                prices: vec![],
-                aggregate: 0.0,
-            },
-        );
+        pairs_cache.entry(pair.key()).or_insert(PairsCache {
+            prices: vec![],
+            aggregate: None,
+            failed_exchanges: vec![],
+        });
+    }
+}
+
+/// remove pairs which are not watched any more from hashmap
+pub fn remove_pairs(pairs: Vec<String>, pairs_cache: &mut HashMap<String, PairsCache>) {
+    for pair in pairs {
+        if let Some(pair) = Pair::parse(&pair) {
+            pairs_cache.remove(&pair.key());
+        }
     }
 }
 
@@ -157,11 +333,7 @@ async fn write_pairs_cache(pairs: HashMap<String, PairsCache>) -> WSResult<()> {
     for pair in pairs {
         let (key, mut pari_cache) = pair;
 
-        let mut amount = 0.0;
-        for price in &pari_cache.prices {
-            amount += &price.price;
-        }
-        pari_cache.aggregate = amount / pari_cache.prices.len() as f64;
+        pari_cache.aggregate = helpers::aggregate_price(&pari_cache.prices);
         pairs_save.insert(key, pari_cache);
     }
     let content = serde_json::to_string(&pairs_save)?;
@@ -170,6 +342,17 @@ async fn write_pairs_cache(pairs: HashMap<String, PairsCache>) -> WSResult<()> {
     Ok(())
 }
 
+/// write mark prices and funding rates in their own file, nothing when no exchange sent them
+fn write_derivatives_cache(derivatives: &DerivativesCache) -> WSResult<()> {
+    if derivatives.is_empty() {
+        return Ok(());
+    }
+    let content = serde_json::to_string(derivatives)?;
+    fs::write(DERIVATIVES_FILE, &content)?;
+
+    Ok(())
+}
+
 /// Handle Read mode argument and print the aggregate of pairs
 fn handle_read_mode() -> WSResult<()> {
     let content = fs::File::open("exchanges.json")?;
@@ -178,7 +361,45 @@ fn handle_read_mode() -> WSResult<()> {
     for pair in &pairs {
         let (key, pari_cache) = pair;
 
-        println!("pair: {:?} -> aggregate: {:?}", key, pari_cache.aggregate);
+        match pari_cache.aggregate {
+            Some(aggregate) => println!("pair: {:?} -> aggregate: {}", key, aggregate),
+            None => println!("pair: {:?} -> aggregate: no data points", key),
+        }
+        if !pari_cache.failed_exchanges.is_empty() {
+            println!(
+                "pair: {:?} -> without failed exchanges: {:?}",
+                key, pari_cache.failed_exchanges
+            );
+        }
+    }
+
+    // receive time minus exchange event time
+    for (name, stats) in helpers::latency_stats(&pairs) {
+        println!(
+            "exchange: {:?} -> latency ms p50: {} p90: {} p99: {} max: {} ({} prices)",
+            name, stats.p50, stats.p90, stats.p99, stats.max, stats.count
+        );
+    }
+
+    // only written when swap or futures exchanges sent mark prices or funding rates
+    if let Ok(content) = fs::File::open(DERIVATIVES_FILE) {
+        let derivatives: DerivativesCache = serde_json::from_reader(&content)?;
+        let show =
+            |value: Option<Decimal>| value.map_or("-".to_string(), |value| value.to_string());
+        let mut keys: Vec<&String> = derivatives.keys().collect();
+        keys.sort();
+        for key in keys {
+            for (name, point) in helpers::latest_derivatives(&derivatives[key]) {
+                println!(
+                    "pair: {:?} -> exchange: {:?} mark: {} index: {} funding rate: {}",
+                    key,
+                    name,
+                    show(point.mark_price),
+                    show(point.index_price),
+                    show(point.funding_rate)
+                );
+            }
+        }
     }
 
     Ok(())
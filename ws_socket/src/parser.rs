@@ -1,84 +1,42 @@
+use std::io::Read;
+
 use crate::{
     errors::WSError,
-    types::{BinanceResponse, CoinbaseResponse, OkexResponse, ResponseEnum, SocketType, WSResult},
+    exchanges::Exchange,
+    types::{FrameEncoding, ResponseEnum, WSResult},
 };
-use serde_json::Value;
+use flate2::read::{DeflateDecoder, GzDecoder};
 use tokio_tungstenite::tungstenite::{Error, Message};
 
-/// parse message
+/// parse message with the exchange adapter
 pub fn message_parser(
-    socket_type: SocketType,
+    exchange: &mut dyn Exchange,
     msg: Result<Message, Error>,
 ) -> WSResult<ResponseEnum> {
     let message = match msg? {
         Message::Text(s) => Ok(s),
+        Message::Binary(data) => decode_frame(exchange.config().frame_encoding, &data),
         _ => Err(WSError::UnknownResponse),
     }?;
 
     let msg: serde_json::Value = serde_json::from_str(&message)?;
 
-    match socket_type {
-        SocketType::Binance => {
-            let msg = parse_binance_response(msg)?;
-            Ok(ResponseEnum::Binance(msg))
+    exchange.parse_response(msg)
+}
+
+/// decode binary frame to text with the frame encoding of the exchange
+pub fn decode_frame(encoding: FrameEncoding, data: &[u8]) -> WSResult<String> {
+    let mut message = String::new();
+    match encoding {
+        FrameEncoding::Text => {
+            message = String::from_utf8(data.to_vec()).map_err(|_| WSError::UnknownResponse)?;
         }
-        SocketType::Okex => {
-            let msg = parse_okex_response(msg)?;
-            Ok(ResponseEnum::Okex(msg))
+        FrameEncoding::Gzip => {
+            GzDecoder::new(data).read_to_string(&mut message)?;
         }
-        SocketType::Coinbase => {
-            let msg = parse_coinbase_response(msg)?;
-            Ok(ResponseEnum::Coinbase(msg))
+        FrameEncoding::Deflate => {
+            DeflateDecoder::new(data).read_to_string(&mut message)?;
         }
     }
-}
-
-/// parse binance data from socket response
-fn parse_binance_response(msg: Value) -> WSResult<BinanceResponse> {
-    if msg["result"] == "error" {
-        let error = format!("{:?}", msg);
-        return Err(WSError::SocketResponseError(error));
-    }
-    // Serialize binance response
-    let binance_response: BinanceResponse = match serde_json::from_value(msg) {
-        Ok(p) => p,
-        Err(_) => BinanceResponse {
-            s: "".to_string(),
-            c: "0.0".to_string(),
-        },
-    };
-    Ok(binance_response)
-}
-
-/// parse coinbase data from socket response
-fn parse_coinbase_response(msg: Value) -> WSResult<CoinbaseResponse> {
-    if msg["type"] == "error" {
-        let error = format!("{:?}", msg);
-        return Err(WSError::SocketResponseError(error));
-    }
-
-    // Serialize coinbase response
-    let coinbase_response: CoinbaseResponse = match serde_json::from_value(msg) {
-        Ok(p) => p,
-        Err(_) => CoinbaseResponse {
-            product_id: "".to_string(),
-            price: "0.0".to_string(),
-        },
-    };
-    Ok(coinbase_response)
-}
-
-/// parse okex data from socket response
-fn parse_okex_response(msg: Value) -> WSResult<OkexResponse> {
-    if msg["event"] == "error" {
-        let error = format!("{:?}", msg);
-        return Err(WSError::SocketResponseError(error));
-    }
-
-    // Serialize okex response
-    let okex_response: OkexResponse = match serde_json::from_value(msg) {
-        Ok(p) => p,
-        Err(_) => OkexResponse { data: vec![] },
-    };
-    Ok(okex_response)
+    Ok(message)
 }
@@ -0,0 +1,216 @@
+use std::env;
+use std::net::IpAddr;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{self, TcpStream};
+use url::{Host, Url};
+
+use crate::{
+    errors::WSError,
+    types::{WSResult, WebSocketConfig},
+};
+
+/// proxy setting which connects without the proxy of the environment
+pub const DIRECT: &str = "direct";
+
+/// longest proxy response head we read for http connect
+const MAX_RESPONSE_HEAD: usize = 8 * 1024;
+
+/// proxy of the exchange socket, config first then `HTTPS_PROXY` and `ALL_PROXY`
+pub fn proxy_url(config: &WebSocketConfig, url: &Url) -> WSResult<Option<Url>> {
+    resolve_proxy(config, url, |key| env::var(key).ok())
+}
+
+/// proxy of the exchange socket with the environment lookup
+pub fn resolve_proxy(
+    config: &WebSocketConfig,
+    url: &Url,
+    env: impl Fn(&str) -> Option<String>,
+) -> WSResult<Option<Url>> {
+    let setting = match &config.proxy {
+        Some(setting) => Some(setting.clone()),
+        None => {
+            // https proxy only applies to tls sockets
+            let keys: &[&str] = if matches!(url.scheme(), "wss" | "https") {
+                &["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+            } else {
+                &["ALL_PROXY", "all_proxy"]
+            };
+            keys.iter()
+                .filter_map(|key| env(key))
+                .find(|value| !value.is_empty())
+        }
+    };
+
+    match setting {
+        Some(setting) if setting != DIRECT => Ok(Some(parse_proxy(&config.name, &setting)?)),
+        _ => Ok(None),
+    }
+}
+
+/// parse `http://`, `socks5://` or `socks5h://` proxy url
+pub fn parse_proxy(name: &str, setting: &str) -> WSResult<Url> {
+    let invalid_proxy = || WSError::InvalidProxy(name.to_string(), setting.to_string());
+    let url = Url::parse(setting).map_err(|_| invalid_proxy())?;
+    match url.scheme() {
+        "http" | "socks5" | "socks5h" if url.host_str().is_some() => Ok(url),
+        _ => Err(invalid_proxy()),
+    }
+}
+
+/// open tcp stream to the socket host, through the tunnel of the proxy when there is one
+pub async fn open_stream(name: &str, url: &Url, proxy: Option<&Url>) -> WSResult<TcpStream> {
+    let invalid_url = || WSError::InvalidUrl(name.to_string(), url.to_string());
+    let host = url.host().ok_or_else(invalid_url)?;
+    let port = url.port_or_known_default().ok_or_else(invalid_url)?;
+
+    let proxy = match proxy {
+        Some(proxy) => proxy,
+        None => return connect_host(&host, port).await,
+    };
+    let proxy_host = proxy
+        .host()
+        .ok_or_else(|| WSError::InvalidProxy(name.to_string(), proxy.to_string()))?;
+    let proxy_port = proxy.port_or_known_default().unwrap_or(1080);
+
+    let mut stream = connect_host(&proxy_host, proxy_port).await?;
+    match proxy.scheme() {
+        // authority keeps the brackets of an ipv6 address
+        "http" => http_connect(&mut stream, proxy, &host.to_string(), port).await?,
+        scheme => socks5_connect(&mut stream, proxy, &host, port, scheme == "socks5h").await?,
+    }
+    Ok(stream)
+}
+
+/// tcp stream to the host of a url, ipv6 addresses without their brackets
+async fn connect_host(host: &Host<&str>, port: u16) -> WSResult<TcpStream> {
+    let stream = match host {
+        Host::Domain(domain) => TcpStream::connect((*domain, port)).await?,
+        Host::Ipv4(address) => TcpStream::connect((*address, port)).await?,
+        Host::Ipv6(address) => TcpStream::connect((*address, port)).await?,
+    };
+    Ok(stream)
+}
+
+/// length prefix of a socks5 field, which is one byte
+fn socks5_len(field: &str, value: &str) -> WSResult<u8> {
+    u8::try_from(value.len())
+        .map_err(|_| WSError::ProxyError(format!("socks5 {} is longer than 255 bytes", field)))
+}
+
+/// open tunnel with http `CONNECT` request
+async fn http_connect(stream: &mut TcpStream, proxy: &Url, host: &str, port: u16) -> WSResult<()> {
+    let mut request = format!(
+        "CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n",
+        host = host,
+        port = port
+    );
+    if !proxy.username().is_empty() {
+        let credentials = format!("{}:{}", proxy.username(), proxy.password().unwrap_or(""));
+        request.push_str(&format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            base64::encode(credentials)
+        ));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // read byte by byte, anything after the head belongs to the tunnel
+    let mut head = vec![];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_RESPONSE_HEAD {
+            return Err(WSError::ProxyError("proxy response too long".to_string()));
+        }
+        head.push(stream.read_u8().await?);
+    }
+
+    let head = String::from_utf8_lossy(&head);
+    let status = head.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some("200") => Ok(()),
+        _ => Err(WSError::ProxyError(format!("CONNECT refused: {}", status))),
+    }
+}
+
+/// open tunnel with socks5 handshake, host name is resolved by the proxy for `socks5h` and locally for `socks5`
+async fn socks5_connect(
+    stream: &mut TcpStream,
+    proxy: &Url,
+    host: &Host<&str>,
+    port: u16,
+    remote_dns: bool,
+) -> WSResult<()> {
+    let socks_error = |reason: &str| WSError::ProxyError(format!("socks5 {}", reason));
+    let username = proxy.username();
+    let password = proxy.password().unwrap_or("");
+
+    // address is ready before anything is sent to the proxy
+    let mut address = match host {
+        Host::Domain(domain) if remote_dns => {
+            let mut address = vec![3, socks5_len("host", domain)?];
+            address.extend_from_slice(domain.as_bytes());
+            address
+        }
+        Host::Domain(domain) => {
+            let resolved = net::lookup_host((*domain, port))
+                .await?
+                .next()
+                .ok_or_else(|| socks_error(&format!("could not resolve {}", domain)))?;
+            match resolved.ip() {
+                IpAddr::V4(address) => [&[1][..], &address.octets()].concat(),
+                IpAddr::V6(address) => [&[4][..], &address.octets()].concat(),
+            }
+        }
+        Host::Ipv4(address) => [&[1][..], &address.octets()].concat(),
+        Host::Ipv6(address) => [&[4][..], &address.octets()].concat(),
+    };
+    address.extend_from_slice(&port.to_be_bytes());
+
+    // version 5, no auth or username and password
+    if username.is_empty() {
+        stream.write_all(&[5, 1, 0]).await?;
+    } else {
+        stream.write_all(&[5, 2, 0, 2]).await?;
+    }
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    match reply {
+        [5, 0] => {}
+        [5, 2] if !username.is_empty() => {
+            let mut auth = vec![1, socks5_len("username", username)?];
+            auth.extend_from_slice(username.as_bytes());
+            auth.push(socks5_len("password", password)?);
+            auth.extend_from_slice(password.as_bytes());
+            stream.write_all(&auth).await?;
+            stream.read_exact(&mut reply).await?;
+            if reply[1] != 0 {
+                return Err(socks_error("authentication failed"));
+            }
+        }
+        _ => return Err(socks_error("no acceptable auth method")),
+    }
+
+    // connect command with domain name or ip address
+    let mut request = vec![5, 1, 0];
+    request.extend_from_slice(&address);
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0 {
+        return Err(socks_error(&format!(
+            "connect failed with code {}",
+            reply[1]
+        )));
+    }
+    // skip bound address and port
+    let address_len = match reply[3] {
+        1 => 4,
+        3 => stream.read_u8().await? as usize,
+        4 => 16,
+        _ => return Err(socks_error("unknown address type")),
+    };
+    let mut bound = vec![0u8; address_len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
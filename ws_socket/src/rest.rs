@@ -0,0 +1,61 @@
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use url::Url;
+
+use crate::{
+    errors::WSError,
+    proxy, tls,
+    types::{TlsConfig, WSResult, WebSocketConfig},
+};
+
+/// largest rest response we read, a full depth snapshot is a few hundred kilobytes
+const MAX_RESPONSE: u64 = 16 * 1024 * 1024;
+
+/// get json of the rest url with the proxy and tls settings of the exchange
+///
+/// Request is http/1.0, so the body is never chunked and ends when the server closes.
+pub async fn get_json(config: &WebSocketConfig, url: &Url) -> WSResult<Value> {
+    let failed = |reason: &str| WSError::SnapshotFailed(config.name.clone(), reason.to_string());
+    let host = url
+        .host_str()
+        .ok_or_else(|| WSError::InvalidUrl(config.name.clone(), url.to_string()))?;
+    let proxy = proxy::proxy_url(config, url)?;
+    let stream = proxy::open_stream(&config.name, url, proxy.as_ref()).await?;
+    // pins are of the socket host, the rest host has its own certificate
+    let tls = TlsConfig {
+        spki_pins: vec![],
+        ..config.tls.clone()
+    };
+    let mut stream = tls::wrap_stream(&config.name, url, &tls, stream).await?;
+
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    };
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nAccept: application/json\r\n\r\n",
+        path, host
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = vec![];
+    (&mut stream)
+        .take(MAX_RESPONSE)
+        .read_to_end(&mut response)
+        .await?;
+
+    let head_end = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| failed("response has no head"))?;
+    let head = String::from_utf8_lossy(&response[..head_end]);
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    if status != "200" {
+        return Err(failed(&format!("status {}", status)));
+    }
+    Ok(serde_json::from_slice(&response[head_end + 4..])?)
+}
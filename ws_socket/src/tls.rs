@@ -0,0 +1,144 @@
+use std::fs;
+
+use sha2::{Digest, Sha256};
+use tokio::net::TcpStream;
+use tokio_tungstenite::MaybeTlsStream;
+use url::{Host, Url};
+
+use crate::{
+    errors::WSError,
+    types::{TlsConfig, WSResult},
+};
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+compile_error!("enable the native-tls or rustls feature of ws_socket");
+
+/// tls handshake on the stream for `wss` and `https` urls, then check the certificate pins
+pub async fn wrap_stream(
+    name: &str,
+    url: &Url,
+    tls: &TlsConfig,
+    stream: TcpStream,
+) -> WSResult<MaybeTlsStream<TcpStream>> {
+    if !matches!(url.scheme(), "wss" | "https") {
+        return Ok(MaybeTlsStream::Plain(stream));
+    }
+    let host = url
+        .host()
+        .ok_or_else(|| WSError::InvalidUrl(name.to_string(), url.to_string()))?;
+
+    let (stream, leaf) = handshake(&host, tls, stream).await?;
+    if !tls.spki_pins.is_empty() {
+        let leaf = leaf.ok_or_else(|| WSError::PinMismatch(name.to_string()))?;
+        let pin = spki_pin(&leaf)?;
+        if !tls.spki_pins.contains(&pin) {
+            return Err(WSError::PinMismatch(name.to_string()));
+        }
+    }
+    Ok(stream)
+}
+
+/// base64 sha256 of the subject public key info of a der certificate
+pub fn spki_pin(certificate: &[u8]) -> WSResult<String> {
+    let (_, certificate) = x509_parser::parse_x509_certificate(certificate)
+        .map_err(|error| WSError::TlsError(error.to_string()))?;
+    let digest = Sha256::digest(certificate.tbs_certificate.subject_pki.raw);
+    Ok(base64::encode(digest))
+}
+
+/// read every pem certificate of the ca bundles
+fn read_bundles(tls: &TlsConfig) -> WSResult<Vec<Vec<u8>>> {
+    let mut certificates = vec![];
+    for path in &tls.ca_bundles {
+        let pem = fs::read_to_string(path)?;
+        for block in pem.split_inclusive("-----END CERTIFICATE-----") {
+            if let Some(start) = block.find("-----BEGIN CERTIFICATE-----") {
+                certificates.push(block.as_bytes()[start..].to_vec());
+            }
+        }
+    }
+    Ok(certificates)
+}
+
+/// rustls handshake with webpki roots and the ca bundles, return the stream and leaf certificate
+#[cfg(feature = "rustls")]
+async fn handshake(
+    host: &Host<&str>,
+    tls: &TlsConfig,
+    stream: TcpStream,
+) -> WSResult<(MaybeTlsStream<TcpStream>, Option<Vec<u8>>)> {
+    use std::{net::IpAddr, sync::Arc};
+
+    use rustls::{Certificate, ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName};
+    use tokio_rustls::TlsConnector;
+
+    let tls_error = |error: &dyn std::fmt::Display| WSError::TlsError(error.to_string());
+
+    let mut roots = RootCertStore::empty();
+    roots.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|anchor| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(
+            anchor.subject,
+            anchor.spki,
+            anchor.name_constraints,
+        )
+    }));
+    for pem in read_bundles(tls)? {
+        for der in rustls_pemfile::certs(&mut pem.as_slice())? {
+            roots
+                .add(&Certificate(der))
+                .map_err(|error| tls_error(&error))?;
+        }
+    }
+
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let server_name = match host {
+        Host::Domain(domain) => ServerName::try_from(*domain).map_err(|error| tls_error(&error))?,
+        Host::Ipv4(ip) => ServerName::IpAddress(IpAddr::V4(*ip)),
+        Host::Ipv6(ip) => ServerName::IpAddress(IpAddr::V6(*ip)),
+    };
+    let stream = TlsConnector::from(Arc::new(config))
+        .connect(server_name, stream)
+        .await?;
+
+    let leaf = stream
+        .get_ref()
+        .1
+        .peer_certificates()
+        .and_then(|certificates| certificates.first())
+        .map(|certificate| certificate.0.clone());
+    Ok((MaybeTlsStream::Rustls(stream), leaf))
+}
+
+/// native tls handshake with system roots and the ca bundles, return the stream and leaf certificate
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+async fn handshake(
+    host: &Host<&str>,
+    tls: &TlsConfig,
+    stream: TcpStream,
+) -> WSResult<(MaybeTlsStream<TcpStream>, Option<Vec<u8>>)> {
+    use native_tls::{Certificate, TlsConnector};
+
+    let tls_error = |error: native_tls::Error| WSError::TlsError(error.to_string());
+
+    let mut builder = TlsConnector::builder();
+    for pem in read_bundles(tls)? {
+        builder.add_root_certificate(Certificate::from_pem(&pem).map_err(tls_error)?);
+    }
+    let connector = tokio_native_tls::TlsConnector::from(builder.build().map_err(tls_error)?);
+    // ip literals without the brackets of the url
+    let host = match host {
+        Host::Domain(domain) => domain.to_string(),
+        Host::Ipv4(ip) => ip.to_string(),
+        Host::Ipv6(ip) => ip.to_string(),
+    };
+    let stream = connector.connect(&host, stream).await.map_err(tls_error)?;
+
+    let leaf = match stream.get_ref().peer_certificate().map_err(tls_error)? {
+        Some(certificate) => Some(certificate.to_der().map_err(tls_error)?),
+        None => None,
+    };
+    Ok((MaybeTlsStream::NativeTls(stream), leaf))
+}
@@ -1,12 +1,14 @@
 pub use clap::Parser;
-use futures_util::{SinkExt, StreamExt};
+use rand::Rng;
+pub use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tokio::net::TcpStream;
-use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
-use url::Url;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::{errors::WSError, helpers};
+use crate::errors::WSError;
 
 #[derive(Parser, Debug)]
 #[clap(author = "Nizam", version, about)]
@@ -27,6 +29,302 @@ pub struct WebSocketConfig {
     pub name: String,
     pub ws_base_url: String,
     pub req_param: Value,
+    #[serde(default)]
+    pub frame_encoding: FrameEncoding,
+    /// field mapping for an exchange without its own adapter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generic: Option<GenericConfig>,
+    #[serde(default)]
+    pub reconnect: ReconnectPolicy,
+    /// keepalive of the exchange adapter is used when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keepalive: Option<Keepalive>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stale: Option<StalePolicy>,
+    /// `http://`, `socks5://` or `socks5h://` proxy url, `direct` skips the proxy of the environment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    #[serde(default)]
+    pub tls: TlsConfig,
+    /// pairs are split over more connections above this, limit of the adapter is used when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_pairs_per_connection: Option<usize>,
+    /// how long connect and subscribe may take before the exchange is left out
+    #[serde(default = "default_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    /// how long subscribe waits for the acks of the exchange
+    #[serde(default = "default_subscribe_timeout_ms")]
+    pub subscribe_timeout_ms: u64,
+    /// venue symbols which differ from the canonical pair
+    #[serde(default)]
+    pub symbols: SymbolMap,
+    /// requested quote to the quote the venue lists, like `USDT` to `USD`
+    ///
+    /// Prices are converted with the rate of the conversion pair, which is subscribed too.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub bridges: HashMap<String, String>,
+    /// market data stream to subscribe, tickers when not set
+    #[serde(default)]
+    pub stream: StreamKind,
+    /// book price used for the aggregate, mid when not set
+    #[serde(default)]
+    pub book_price: BookPrice,
+    /// rest api url of order book snapshots, adapter default when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rest_base_url: Option<String>,
+    /// market of the instruments, spot when not set
+    #[serde(default)]
+    pub market: MarketType,
+    /// expiry of dated futures as `YYMMDD`, like `231229`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiry: Option<String>,
+    /// socket url of swap and futures markets when the venue serves them apart, adapter default when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub futures_ws_base_url: Option<String>,
+    /// subscribe mark price and funding rate of swap and futures markets too
+    #[serde(default)]
+    pub mark_funding: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// market of the instruments of an exchange
+pub enum MarketType {
+    #[default]
+    Spot,
+    /// perpetual swap, linear in the quote
+    Swap,
+    /// dated futures of the configured expiry
+    Futures,
+}
+
+impl fmt::Display for MarketType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let market = match self {
+            MarketType::Spot => "spot",
+            MarketType::Swap => "swap",
+            MarketType::Futures => "futures",
+        };
+        write!(f, "{}", market)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// market data stream of an exchange
+pub enum StreamKind {
+    #[default]
+    Ticker,
+    /// every executed trade
+    Trades,
+    /// trades of one taker order merged, binance only
+    AggTrades,
+    /// level 2 order book kept in memory, priced from its top
+    Book,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+/// price of an order book
+pub enum BookPrice {
+    /// middle of best bid and best ask
+    #[default]
+    Mid,
+    /// mid weighted towards the side with less size
+    Microprice,
+}
+
+impl fmt::Display for StreamKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stream = match self {
+            StreamKind::Ticker => "ticker",
+            StreamKind::Trades => "trades",
+            StreamKind::AggTrades => "agg_trades",
+            StreamKind::Book => "book",
+        };
+        write!(f, "{}", stream)
+    }
+}
+
+fn default_connect_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_subscribe_timeout_ms() -> u64 {
+    5_000
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// canonical pair of upper case base and quote asset
+pub struct Pair {
+    pub base: String,
+    pub quote: String,
+}
+
+impl Pair {
+    pub fn new(base: &str, quote: &str) -> Pair {
+        Pair {
+            base: base.to_uppercase(),
+            quote: quote.to_uppercase(),
+        }
+    }
+
+    /// parse `btc_usdt` style pair, none unless there are two assets
+    pub fn parse(pair: &str) -> Option<Pair> {
+        match pair.split_once('_') {
+            Some((base, quote))
+                if !base.is_empty() && !quote.is_empty() && !quote.contains('_') =>
+            {
+                Some(Pair::new(base, quote))
+            }
+            _ => None,
+        }
+    }
+
+    /// pairs cache key like `BTCUSDT`
+    pub fn key(&self) -> String {
+        format!("{}{}", self.base, self.quote)
+    }
+}
+
+impl fmt::Display for Pair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.base, self.quote)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+/// mapping of canonical pairs to venue symbols
+pub struct SymbolMap {
+    /// venue name of an asset, like `BTC` to `XBT`, or `USDT` to `USD`
+    pub assets: HashMap<String, String>,
+    /// whole venue symbol of a pair like `btc_usdt`, used as it is
+    pub pairs: HashMap<String, String>,
+}
+
+impl SymbolMap {
+    /// venue name of the asset
+    pub fn asset<'a>(&'a self, asset: &'a str) -> &'a str {
+        self.assets
+            .iter()
+            .find(|(canonical, _)| canonical.eq_ignore_ascii_case(asset))
+            .map_or(asset, |(_, venue)| venue.as_str())
+    }
+
+    /// venue symbol set for the pair
+    pub fn pair(&self, pair: &Pair) -> Option<&str> {
+        let name = pair.to_string();
+        self.pairs
+            .iter()
+            .find(|(canonical, _)| canonical.eq_ignore_ascii_case(&name))
+            .map(|(_, symbol)| symbol.as_str())
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+/// tls settings of a socket
+pub struct TlsConfig {
+    /// pem files with extra root certificates
+    pub ca_bundles: Vec<String>,
+    /// base64 sha256 of the server certificate public key, any of them must match
+    pub spki_pins: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+/// reconnect backoff of a socket, delay doubles on every failed attempt
+pub struct ReconnectPolicy {
+    pub initial_delay_ms: u64,
+    pub max_delay_ms: u64,
+    /// give up after this many failed attempts in a row, retry forever when not set
+    pub max_retries: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            initial_delay_ms: 500,
+            max_delay_ms: 30_000,
+            max_retries: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// delay before retry attempt, jittered between half and full exponential delay
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .initial_delay_ms
+            .saturating_mul(2u64.saturating_pow(attempt))
+            .min(self.max_delay_ms);
+        let jitter: f64 = rand::thread_rng().gen_range(0.5..=1.0);
+        Duration::from_millis((delay as f64 * jitter) as u64)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// generic exchange field mapping, paths are json pointers
+pub struct GenericConfig {
+    /// array in req_param where subscribed pairs are pushed
+    pub pairs_path: String,
+    /// value pushed for every pair, `{symbol}` is replaced with the exchange symbol
+    #[serde(default = "default_pair_template")]
+    pub pair_template: Value,
+    #[serde(default)]
+    pub symbol: SymbolFormat,
+    /// ticker object or array of tickers in the response, whole response when empty
+    #[serde(default)]
+    pub data_path: String,
+    /// symbol in the ticker
+    pub symbol_path: String,
+    /// price in the ticker, string or number
+    pub price_path: String,
+    pub error: Option<ErrorRule>,
+}
+
+fn default_pair_template() -> Value {
+    Value::String("{symbol}".to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+/// exchange symbol format
+pub struct SymbolFormat {
+    #[serde(default)]
+    pub separator: String,
+    #[serde(default)]
+    pub case: SymbolCase,
+    #[serde(default)]
+    pub prefix: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+/// letter case of exchange symbol
+pub enum SymbolCase {
+    #[default]
+    Upper,
+    Lower,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// response is an error when the value at path is `equals`, or present and not null without `equals`
+pub struct ErrorRule {
+    pub path: String,
+    pub equals: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+/// encoding of binary socket frames
+pub enum FrameEncoding {
+    /// utf-8 json without compression
+    #[default]
+    Text,
+    Gzip,
+    /// raw deflate stream without zlib header
+    Deflate,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -61,18 +359,234 @@ pub struct OkexReqParamArg {
     pub inst_id: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+/// kraken request parameter structure
+pub struct KrakenReqParam {
+    pub method: String,
+    pub params: KrakenReqParamParams,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// kraken request parameter params structure
+pub struct KrakenReqParamParams {
+    pub channel: String,
+    pub symbol: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// bybit request parameter structure
+pub struct BybitReqParam {
+    pub op: String,
+    pub args: Vec<String>,
+    /// symbols of the args, bybit echoes it in the reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub req_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// bitfinex request parameter structure, one request per symbol
+pub struct BitfinexReqParam {
+    pub event: String,
+    pub channel: String,
+    #[serde(default)]
+    pub symbol: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// htx request parameter structure, one request per symbol
+pub struct HtxReqParam {
+    #[serde(default)]
+    pub sub: String,
+    pub id: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 /// pairs cache structure
 pub struct PairsCache {
     pub prices: Vec<PricesPairs>,
-    pub aggregate: f64,
+    /// mean of the prices, null without data points
+    #[serde(default)]
+    pub aggregate: Option<Decimal>,
+    /// exchanges which could not connect, aggregate is without them
+    #[serde(default)]
+    pub failed_exchanges: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 /// prices pairs structure
 pub struct PricesPairs {
     pub name: String,
-    pub price: f64,
+    /// last price with the precision of the exchange
+    pub price: Decimal,
+    /// full ticker of the exchange, none in caches written before it was kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticker: Option<Ticker>,
+    /// wall clock receive time in milliseconds since epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received_at: Option<u64>,
+    /// monotonic receive time in microseconds since the process started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received_mono_us: Option<u64>,
+    /// exchange time of the event in milliseconds since epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_time: Option<u64>,
+    /// rate the venue price was converted with, none without quote bridge
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conversion: Option<Conversion>,
+    /// trade of the price in trades mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trade: Option<Trade>,
+    /// market of a swap or futures price, none for spot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub market: Option<MarketType>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// mark price and funding rate of a swap or futures pair, kept apart from prices
+pub struct DerivativePoint {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mark_price: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_price: Option<Decimal>,
+    /// rate of the next funding, swaps only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub funding_rate: Option<Decimal>,
+    /// next funding time in milliseconds since epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub funding_time: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_time: Option<u64>,
+}
+
+/// mark and funding points keyed by pairs cache key
+pub type DerivativesCache = HashMap<String, Vec<DerivativePoint>>;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+/// side of the taker of a trade
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// executed trade, values are kept as the exchange sent them
+pub struct Trade {
+    pub id: String,
+    pub price: String,
+    pub size: String,
+    pub side: TradeSide,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// conversion of a venue price into the requested quote, price is divided by the rate
+pub struct Conversion {
+    /// conversion pair like `USDT_USD`
+    pub pair: String,
+    /// venue quote per requested quote
+    pub rate: Decimal,
+}
+
+#[cfg(feature = "f64")]
+impl PairsCache {
+    /// aggregate as float, it may lose precision
+    pub fn aggregate_f64(&self) -> Option<f64> {
+        let aggregate = self.aggregate?;
+        Some(rust_decimal::prelude::ToPrimitive::to_f64(&aggregate).unwrap_or(f64::NAN))
+    }
+}
+
+impl PricesPairs {
+    /// price as float, it may lose precision
+    #[cfg(feature = "f64")]
+    pub fn price_f64(&self) -> f64 {
+        rust_decimal::prelude::ToPrimitive::to_f64(&self.price).unwrap_or(f64::NAN)
+    }
+
+    /// receive time minus exchange event time in milliseconds
+    pub fn latency_ms(&self) -> Option<i64> {
+        let received_at = i64::try_from(self.received_at?).ok()?;
+        let event_time = i64::try_from(self.event_time?).ok()?;
+        Some(received_at - event_time)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// local time a socket message was read
+pub struct ReceiveTime {
+    pub instant: Instant,
+    pub wall: SystemTime,
+}
+
+/// base of monotonic receive times
+static PROCESS_START: OnceLock<Instant> = OnceLock::new();
+
+impl ReceiveTime {
+    pub fn now() -> ReceiveTime {
+        PROCESS_START.get_or_init(Instant::now);
+        ReceiveTime {
+            instant: Instant::now(),
+            wall: SystemTime::now(),
+        }
+    }
+
+    /// microseconds since the process started
+    pub fn monotonic_us(&self) -> u64 {
+        let start = *PROCESS_START.get_or_init(Instant::now);
+        let elapsed = self.instant.saturating_duration_since(start);
+        u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX)
+    }
+
+    /// milliseconds since epoch
+    pub fn wall_ms(&self) -> u64 {
+        let elapsed = self.wall.duration_since(UNIX_EPOCH).unwrap_or_default();
+        u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// feed latency of an exchange in milliseconds, nearest rank percentiles
+pub struct LatencyStats {
+    pub count: usize,
+    pub p50: i64,
+    pub p90: i64,
+    pub p99: i64,
+    pub max: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+/// ticker normalised over exchanges, values are kept as the exchange sent them
+///
+/// Fields the exchange does not send are none.
+pub struct Ticker {
+    pub last: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ask: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bid_size: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ask_size: Option<String>,
+    /// base volume of the last 24 hours
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume_24h: Option<String>,
+    /// exchange time of the event in milliseconds since epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_time: Option<u64>,
+}
+
+impl Ticker {
+    /// ticker with the last price only
+    pub fn new(last: impl Into<String>) -> Ticker {
+        Ticker {
+            last: last.into(),
+            ..Default::default()
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -80,6 +594,18 @@ pub struct PricesPairs {
 pub struct BinanceResponse {
     pub s: String,
     pub c: String,
+    #[serde(default)]
+    pub b: Option<String>,
+    #[serde(default, rename = "B")]
+    pub bid_size: Option<String>,
+    #[serde(default)]
+    pub a: Option<String>,
+    #[serde(default, rename = "A")]
+    pub ask_size: Option<String>,
+    #[serde(default)]
+    pub v: Option<String>,
+    #[serde(default, rename = "E")]
+    pub event_time: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -87,6 +613,19 @@ pub struct BinanceResponse {
 pub struct CoinbaseResponse {
     pub product_id: String,
     pub price: String,
+    #[serde(default)]
+    pub best_bid: Option<String>,
+    #[serde(default)]
+    pub best_bid_size: Option<String>,
+    #[serde(default)]
+    pub best_ask: Option<String>,
+    #[serde(default)]
+    pub best_ask_size: Option<String>,
+    #[serde(default)]
+    pub volume_24h: Option<String>,
+    /// rfc 3339 time of the event
+    #[serde(default)]
+    pub time: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -95,6 +634,19 @@ pub struct OkexResponseChild {
     #[serde(rename = "instId")]
     pub inst_id: String,
     pub last: String,
+    #[serde(default, rename = "bidPx")]
+    pub bid_px: Option<String>,
+    #[serde(default, rename = "bidSz")]
+    pub bid_sz: Option<String>,
+    #[serde(default, rename = "askPx")]
+    pub ask_px: Option<String>,
+    #[serde(default, rename = "askSz")]
+    pub ask_sz: Option<String>,
+    #[serde(default, rename = "vol24h")]
+    pub vol_24h: Option<String>,
+    /// milliseconds since epoch as string
+    #[serde(default)]
+    pub ts: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -103,113 +655,362 @@ pub struct OkexResponse {
     pub data: Vec<OkexResponseChild>,
 }
 
-pub type WSResult<T> = Result<T, WSError>;
+#[derive(Debug, Serialize, Deserialize)]
+/// binance trade and aggregate trade structure
+pub struct BinanceTrade {
+    pub e: String,
+    pub s: String,
+    pub p: String,
+    pub q: String,
+    /// trade id of `trade`
+    #[serde(default)]
+    pub t: Option<u64>,
+    /// aggregate trade id of `aggTrade`
+    #[serde(default)]
+    pub a: Option<u64>,
+    #[serde(rename = "T")]
+    pub trade_time: u64,
+    /// buyer is the maker, so the taker sold
+    pub m: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// coinbase match structure
+pub struct CoinbaseMatch {
+    pub product_id: String,
+    pub trade_id: u64,
+    pub price: String,
+    pub size: String,
+    /// side of the maker order
+    pub side: TradeSide,
+    #[serde(default)]
+    pub time: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// okex trade structure
+pub struct OkexTrade {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "tradeId")]
+    pub trade_id: String,
+    pub px: String,
+    pub sz: String,
+    /// side of the taker
+    pub side: TradeSide,
+    pub ts: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// okex trades parent structure
+pub struct OkexTrades {
+    pub data: Vec<OkexTrade>,
+}
+
+/// price and size of a book level
+pub type BookLevel = (String, String);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// binance diff depth structure
+pub struct BinanceDepth {
+    pub s: String,
+    /// first update id of the event
+    #[serde(rename = "U")]
+    pub first_update_id: u64,
+    /// last update id of the event
+    pub u: u64,
+    /// last update id of the previous event, only usd-m streams send it
+    #[serde(default)]
+    pub pu: Option<u64>,
+    pub b: Vec<BookLevel>,
+    pub a: Vec<BookLevel>,
+    #[serde(rename = "E", default)]
+    pub event_time: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// binance rest depth snapshot structure
+pub struct BinanceDepthSnapshot {
+    #[serde(rename = "lastUpdateId")]
+    pub last_update_id: u64,
+    pub bids: Vec<BookLevel>,
+    pub asks: Vec<BookLevel>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// coinbase level2 snapshot structure
+pub struct CoinbaseSnapshot {
+    pub product_id: String,
+    pub bids: Vec<BookLevel>,
+    pub asks: Vec<BookLevel>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// coinbase level2 update structure, changes are side, price and size
+pub struct CoinbaseL2Update {
+    pub product_id: String,
+    pub changes: Vec<(String, String, String)>,
+    #[serde(default)]
+    pub time: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// okex books child structure, levels are price, size, deprecated and order count
+pub struct OkexBook {
+    pub asks: Vec<(String, String, String, String)>,
+    pub bids: Vec<(String, String, String, String)>,
+    pub ts: String,
+    pub checksum: i32,
+    #[serde(rename = "seqId", default)]
+    pub seq_id: Option<i64>,
+    #[serde(rename = "prevSeqId", default)]
+    pub prev_seq_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// binance futures mark price structure, funding rate is empty for dated futures
+pub struct BinanceMarkPrice {
+    pub s: String,
+    /// mark price
+    pub p: String,
+    /// index price
+    #[serde(default)]
+    pub i: Option<String>,
+    /// funding rate
+    #[serde(default)]
+    pub r: Option<String>,
+    /// next funding time
+    #[serde(rename = "T", default)]
+    pub funding_time: Option<u64>,
+    #[serde(rename = "E", default)]
+    pub event_time: Option<u64>,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
-pub enum SocketType {
-    Binance,
-    Okex,
-    Coinbase,
+/// okex mark price structure
+pub struct OkexMarkPrice {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "markPx")]
+    pub mark_px: String,
+    #[serde(default)]
+    pub ts: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// okex funding rate structure, funding time is the next settlement
+pub struct OkexFundingRate {
+    #[serde(rename = "instId")]
+    pub inst_id: String,
+    #[serde(rename = "fundingRate")]
+    pub funding_rate: String,
+    #[serde(rename = "fundingTime", default)]
+    pub funding_time: Option<String>,
+    #[serde(default)]
+    pub ts: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// okex data parent structure of the channels without own parent
+pub struct OkexData<T> {
+    pub data: Vec<T>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// okex books parent structure
+pub struct OkexBooks {
+    pub arg: OkexReqParamArg,
+    pub action: String,
+    pub data: Vec<OkexBook>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// bybit socket response child structure
+pub struct BybitResponseChild {
+    pub symbol: String,
+    #[serde(rename = "lastPrice")]
+    pub last_price: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+/// bybit socket response parent structure
+pub struct BybitResponse {
+    pub topic: String,
+    pub data: BybitResponseChild,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// bitfinex subscribed event structure
+pub struct BitfinexSubscribed {
+    pub event: String,
+    #[serde(rename = "chanId")]
+    pub chan_id: u64,
+    pub symbol: String,
+}
+
+pub type WSResult<T> = Result<T, WSError>;
+
+/// socket configs keyed by exchange name
+pub type WSDetails = HashMap<String, WebSocketConfig>;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// price of a pair parsed from socket response
+pub struct PriceUpdate {
+    /// pairs cache key
+    pub key: String,
+    pub ticker: Ticker,
+    /// set by the handler when the message is read
+    #[serde(skip)]
+    pub received: Option<ReceiveTime>,
+    /// set by the quote bridge of the handler
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conversion: Option<Conversion>,
+    /// trade of the price in trades mode, ticker has its price and time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trade: Option<Trade>,
+    /// set by the handler for swap and futures prices
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub market: Option<MarketType>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+/// mark price or funding rate of a pair parsed from socket response, values as the exchange sent them
+pub struct DerivativeUpdate {
+    /// pairs cache key
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mark_price: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index_price: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub funding_rate: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub funding_time: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_time: Option<u64>,
+    /// set by the handler when the message is read
+    #[serde(skip)]
+    pub received: Option<ReceiveTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// parsed socket response
 pub enum ResponseEnum {
-    Binance(BinanceResponse),
-    Okex(OkexResponse),
-    Coinbase(CoinbaseResponse),
-}
-
-#[derive(Debug)]
-pub struct WSHandler {
-    config: WebSocketConfig,
-    socket_type: SocketType,
-    pairs: Vec<String>,
-    pub socket_stream: Option<WebSocketStream<MaybeTlsStream<TcpStream>>>,
-}
-
-impl WSHandler {
-    pub fn new(config: &WebSocketConfig, socket_type: SocketType, pairs: Vec<String>) -> WSHandler {
-        WSHandler {
-            config: config.clone(),
-            socket_type,
-            pairs,
-            socket_stream: None,
-        }
+    Prices(Vec<PriceUpdate>),
+    /// message which must be sent back on the same socket, like pong
+    Reply(String),
+    /// subscription acknowledgement of the exchange
+    Ack(SubscribeAck),
+    /// order book of the exchange symbol is out of sync and must be loaded again
+    Resync(String),
+    /// mark price and funding rate of swap and futures pairs
+    Derivatives(Vec<DerivativeUpdate>),
+    /// any other message without price
+    Ignore,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// subscription acknowledgement, symbols are exchange symbols
+pub enum SubscribeAck {
+    /// every pair of the subscribe request is accepted
+    All,
+    Accepted(Vec<String>),
+    /// rejected symbols with reason, without symbols they are looked up in the reason
+    Rejected(Vec<String>, String),
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+/// pairs accepted and rejected by an exchange on subscribe
+pub struct SubscribeReport {
+    pub accepted: Vec<String>,
+    /// pair and reason
+    pub rejected: Vec<(String, String)>,
+    /// no ack within the subscribe timeout
+    pub pending: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+/// connection state of an exchange socket
+pub enum ConnectionState {
+    #[default]
+    Disconnected,
+    Connecting,
+    Subscribing,
+    /// subscribed, no price yet
+    Subscribed,
+    Streaming,
+    /// no price within the stale timeout
+    Stale,
+    Reconnecting,
+    /// closed on shutdown
+    Closed,
+    /// connect or reconnect gave up
+    Failed,
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self {
+            ConnectionState::Disconnected => "disconnected",
+            ConnectionState::Connecting => "connecting",
+            ConnectionState::Subscribing => "subscribing",
+            ConnectionState::Subscribed => "subscribed",
+            ConnectionState::Streaming => "streaming",
+            ConnectionState::Stale => "stale",
+            ConnectionState::Reconnecting => "reconnecting",
+            ConnectionState::Closed => "closed",
+            ConnectionState::Failed => "failed",
+        };
+        write!(f, "{}", state)
     }
+}
 
-    /// connect to web socket
-    pub async fn connect(&mut self) -> WSResult<()> {
-        match self.socket_type {
-            SocketType::Binance => {
-                let binance_ws_api: String =
-                    helpers::binance_req_url(&self.config.ws_base_url, &self.pairs);
-                let binance_url_parse = Url::parse(&binance_ws_api)?;
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// state change of an exchange socket
+pub struct StateEvent {
+    /// exchange name with the connection number
+    pub exchange: String,
+    pub from: ConnectionState,
+    pub to: ConnectionState,
+    pub at: SystemTime,
+}
 
-                let (binance_socket, _binance_response) = connect_async(binance_url_parse).await?;
-                self.socket_stream = Some(binance_socket);
-            }
-            SocketType::Okex => {
-                let okex_url_parse = Url::parse(&self.config.ws_base_url)?;
-                let (okex_socket, _okex_response) = connect_async(okex_url_parse).await?;
-                self.socket_stream = Some(okex_socket);
-            }
-            SocketType::Coinbase => {
-                let coinbase_url_parse = Url::parse(&self.config.ws_base_url)?;
-                let (coinbase_socket, _coinbase_response) =
-                    connect_async(coinbase_url_parse).await?;
-                self.socket_stream = Some(coinbase_socket);
-            }
-        }
-        Ok(())
-    }
-
-    /// subscribe web socket
-    pub async fn subscribe(&mut self) -> WSResult<()> {
-        match self.socket_type {
-            SocketType::Binance => {
-                let binance_socket = self
-                    .socket_stream
-                    .as_mut()
-                    .expect("There is some issue in binance socket");
-                let (mut _binance_write, _) = binance_socket.split();
-                let binance_req_param: String = helpers::create_req_params(
-                    SocketType::Binance,
-                    &self.config.req_param,
-                    &self.pairs,
-                )?;
-                _binance_write
-                    .send(Message::Text(binance_req_param))
-                    .await?;
-            }
-            SocketType::Okex => {
-                let okex_socket = self
-                    .socket_stream
-                    .as_mut()
-                    .expect("There is some issue in okex socket");
-                let okex_req_param: String = helpers::create_req_params(
-                    SocketType::Okex,
-                    &self.config.req_param,
-                    &self.pairs,
-                )?;
-                let (mut okex_write, _) = okex_socket.split();
-                okex_write.send(Message::Text(okex_req_param)).await?;
-            }
-            SocketType::Coinbase => {
-                let coinbase_socket = self
-                    .socket_stream
-                    .as_mut()
-                    .expect("There is some issue in binance socket");
-                let coinbase_req_param: String = helpers::create_req_params(
-                    SocketType::Coinbase,
-                    &self.config.req_param,
-                    &self.pairs,
-                )?;
-                let (mut coinbase_write, _) = coinbase_socket.split();
-                coinbase_write
-                    .send(Message::Text(coinbase_req_param))
-                    .await?;
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+/// what the client does to keep an idle socket open
+pub enum Keepalive {
+    /// nothing, exchange pings are answered by the socket itself
+    None,
+    /// websocket ping frame
+    Ping { interval_ms: u64 },
+    /// application level text message, `pong` is the answer when it is not json
+    Text {
+        interval_ms: u64,
+        message: String,
+        pong: Option<String>,
+    },
+    /// heartbeat channel of the exchange is subscribed with the pairs
+    Channel,
+}
+
+impl Keepalive {
+    /// how often the client sends its ping
+    pub fn interval(&self) -> Option<Duration> {
+        match self {
+            Keepalive::Ping { interval_ms } | Keepalive::Text { interval_ms, .. } => {
+                Some(Duration::from_millis(*interval_ms))
             }
+            Keepalive::None | Keepalive::Channel => None,
         }
-        Ok(())
     }
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// feed is stale when no price arrives within the timeout
+pub struct StalePolicy {
+    pub timeout_ms: u64,
+    /// reconnect the socket once the feed is stale
+    #[serde(default)]
+    pub reconnect: bool,
+}